@@ -0,0 +1,87 @@
+//! Scanner throughput over synthetic sources of growing size.
+//!
+//! Run with `cargo run --release --example scanner_bench`. Every size doubles
+//! the previous one, so linear scanning keeps the time per byte flat; the
+//! benchmark fails when the largest source is much slower per byte than the
+//! smallest.
+
+use std::time::{Duration, Instant};
+
+use interpreter_starter_rust::scanner::Scanner;
+
+const SIZES: [usize; 6] = [64 << 10, 128 << 10, 256 << 10, 512 << 10, 1 << 20, 2 << 20];
+const RUNS: usize = 3;
+/// Allowed growth of the time per byte between the smallest and largest source.
+const MAX_SLOWDOWN: f64 = 2.0;
+
+const CHUNK: &str = r#"// Counts down and greets everyone.
+fun countdown(n) {
+    while (n > 0) {
+        print n;
+        n = n - 1;
+    }
+    return "liftoff — décollage";
+}
+
+class Greeter {
+    init(name) {
+        this.name = name;
+    }
+
+    greet() {
+        print "Hello, " + this.name + "!";
+    }
+}
+
+var total = 0;
+for (var i = 0; i < 10; i = i + 1) {
+    total = total + i * 3.25 / 2;
+}
+if (total >= 100 and !false or nil == nil) Greeter("world").greet();
+"#;
+
+fn synthetic_source(size: usize) -> String {
+    CHUNK.repeat(size.div_ceil(CHUNK.len()))
+}
+
+fn scan(source: &str) -> (Duration, usize) {
+    let mut best = Duration::MAX;
+    let mut tokens = 0;
+
+    for _ in 0..RUNS {
+        let mut scanner = Scanner::new(source.to_owned());
+
+        let started = Instant::now();
+        scanner.scan_tokens();
+        best = best.min(started.elapsed());
+
+        tokens = scanner.tokens.len();
+    }
+
+    (best, tokens)
+}
+
+fn main() {
+    println!("{:>10} {:>10} {:>10} {:>10} {:>8}", "bytes", "tokens", "ms", "MB/s", "ns/byte");
+
+    let mut per_byte = Vec::new();
+
+    for size in SIZES {
+        let source = synthetic_source(size);
+        let (elapsed, tokens) = scan(&source);
+
+        let nanos = elapsed.as_nanos() as f64 / source.len() as f64;
+        let throughput = source.len() as f64 / elapsed.as_secs_f64() / (1 << 20) as f64;
+
+        println!("{:>10} {:>10} {:>10.2} {:>10.1} {:>8.2}", source.len(), tokens, elapsed.as_secs_f64() * 1000.0, throughput, nanos);
+        per_byte.push(nanos);
+    }
+
+    let slowdown = per_byte[per_byte.len() - 1] / per_byte[0];
+    println!("slowdown per byte from smallest to largest: {:.2}x", slowdown);
+
+    if slowdown > MAX_SLOWDOWN {
+        eprintln!("Scanning does not scale linearly.");
+        std::process::exit(1);
+    }
+}
@@ -1,48 +1,216 @@
-use crate::{environment::Object, expression::{Expr, ExprVisitor}};
+use crate::{environment::Object, expression::{Expr, ExprVisitor}, statement::{Stmt, StmtVisitor}, token::Token};
+
+#[derive(Default)]
 pub struct AstPrinter{}
 
 impl AstPrinter {
-    pub fn print(&mut self, expr: Box<Expr>) -> String{
+    pub fn print(&mut self, expr: &Expr) -> String{
         expr.accept(self)
     }
 
+    pub fn print_stmt(&mut self, stmt: &Stmt) -> String{
+        stmt.accept(self)
+    }
+
+    pub fn print_program(&mut self, stmts: &[Box<Stmt>]) -> String{
+        stmts.iter()
+            .map(|stmt| self.print_stmt(stmt))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
     pub fn new() -> Self {
         Self {  }
     }
 }
 
 impl AstPrinter {
-    fn parenthesize(&mut self, name: String, expressions: Box<[&Box<Expr>]>) -> String{
+    fn parenthesize(&mut self, name: String, expressions: &[&Expr]) -> String{
         let mut builder = String::from("(");
         builder.push_str(&name);
-        
+
         for expr in expressions.iter(){
-            builder.push_str(" ");
+            builder.push(' ');
             builder.push_str(expr.accept(self).as_str());
         }
-        builder.push_str(")");
+        builder.push(')');
+
+        builder
+    }
+
+    fn parenthesize_parts(&mut self, name: &str, parts: &[String]) -> String{
+        let mut builder = String::from("(");
+        builder.push_str(name);
+
+        for part in parts.iter(){
+            builder.push(' ');
+            builder.push_str(part);
+        }
+        builder.push(')');
+
+        builder
+    }
+
+    fn print_block(&mut self, statements: &[Box<Stmt>]) -> Vec<String>{
+        statements.iter()
+            .map(|stmt| self.print_stmt(stmt))
+            .collect()
+    }
+
+    fn print_params(params: &[Token]) -> String{
+        let names = params.iter()
+            .map(|param| param.lexeme.to_owned())
+            .collect::<Vec<String>>()
+            .join(" ");
 
-        return builder;
+        format!("({})", names)
     }
 }
 
 impl ExprVisitor<String> for AstPrinter {
     fn visit(&mut self, expr: &Expr) -> String {
             match expr {
+                Expr::Assign { name, value, .. } => {
+                    let value = self.print(value);
+                    self.parenthesize_parts("=", &[name.lexeme.to_owned(), value])
+                },
                 Expr::Binary { left, operator, right } => {
-                    self.parenthesize(operator.lexeme.clone(), vec![left, right].into_boxed_slice())
+                    self.parenthesize(operator.lexeme.clone(), &[left, right])
+                },
+                Expr::Call { callee, arguments, .. } => {
+                    let mut parts = vec![self.print(callee)];
+                    parts.extend(arguments.iter().map(|argument| self.print(argument)));
+                    self.parenthesize_parts("call", &parts)
+                },
+                Expr::Get { object, name } => {
+                    let object = self.print(object);
+                    self.parenthesize_parts(".", &[object, name.lexeme.to_owned()])
                 },
-                Expr::Grouping { expression } => {
-                    self.parenthesize(String::from("group"), 
-                                      vec![expression].into_boxed_slice())
+                Expr::Grouping { expression, .. } => {
+                    self.parenthesize(String::from("group"), &[expression])
                 },
-                Expr::Literal { value } => {
+                Expr::Index { object, index, .. } => {
+                    self.parenthesize(String::from("index"), &[object, index])
+                },
+                Expr::Interpolation { parts } => {
+                    let parts = parts.iter()
+                        .map(|part| self.print(part))
+                        .collect::<Vec<String>>();
+                    self.parenthesize_parts("interpolate", &parts)
+                },
+                Expr::Lambda { params, body, .. } => {
+                    let mut parts = vec![Self::print_params(params)];
+                    parts.extend(self.print_block(body));
+                    self.parenthesize_parts("lambda", &parts)
+                },
+                Expr::List { elements, .. } => {
+                    let elements = elements.iter()
+                        .map(|element| self.print(element))
+                        .collect::<Vec<String>>();
+                    self.parenthesize_parts("list", &elements)
+                },
+                Expr::Literal { value, .. } => {
                     value.to_string()
                 },
+                Expr::Map { entries, .. } => {
+                    let entries = entries.iter()
+                        .map(|(key, value)| self.parenthesize(String::from(":"), &[key, value]))
+                        .collect::<Vec<String>>();
+                    self.parenthesize_parts("map", &entries)
+                },
+                Expr::Logical { left, operator, right } => {
+                    self.parenthesize(operator.lexeme.clone(), &[left, right])
+                },
+                Expr::Set { object, name, value } => {
+                    let object = self.print(object);
+                    let value = self.print(value);
+                    self.parenthesize_parts("=", &[format!("(. {} {})", object, name.lexeme), value])
+                },
+                Expr::SetIndex { object, index, value, .. } => {
+                    let target = self.parenthesize(String::from("index"), &[object, index]);
+                    let value = self.print(value);
+                    self.parenthesize_parts("=", &[target, value])
+                },
+                Expr::Super { method, .. } => {
+                    self.parenthesize_parts("super", &[method.lexeme.to_owned()])
+                },
+                Expr::This { .. } => String::from("this"),
                 Expr::Unary { operator, right } => {
-                    self.parenthesize(operator.lexeme.clone(), vec![right].into_boxed_slice())
+                    self.parenthesize(operator.lexeme.clone(), &[right])
                 },
-                _ => { todo!() }
+                Expr::Variable { name, .. } => name.lexeme.to_owned(),
             }
         }
-}
\ No newline at end of file
+}
+
+impl StmtVisitor<String> for AstPrinter {
+    fn visit(&mut self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block { statements, .. } => {
+                let parts = self.print_block(statements);
+                self.parenthesize_parts("block", &parts)
+            },
+            Stmt::Class { name, superclass, methods, .. } => {
+                let mut parts = vec![name.lexeme.to_owned()];
+
+                if let Some(superclass) = superclass {
+                    parts.push(format!("< {}", self.print(superclass)));
+                }
+
+                parts.extend(self.print_block(methods));
+                self.parenthesize_parts("class", &parts)
+            },
+            Stmt::Break { .. } => String::from("(break)"),
+            Stmt::Comment { text, .. } => text.to_owned(),
+            Stmt::Continue { .. } => String::from("(continue)"),
+            Stmt::Expression { expression, .. } => {
+                self.parenthesize(String::from(";"), &[expression])
+            },
+            Stmt::Function { name, params, body, .. } => {
+                let mut parts = vec![format!("{}{}", name.lexeme, Self::print_params(params))];
+                parts.extend(self.print_block(body));
+                self.parenthesize_parts("fun", &parts)
+            },
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                let mut parts = vec![self.print(condition), self.print_stmt(then_branch)];
+
+                match else_branch {
+                    Some(else_branch) => {
+                        parts.push(self.print_stmt(else_branch));
+                        self.parenthesize_parts("if-else", &parts)
+                    },
+                    None => self.parenthesize_parts("if", &parts),
+                }
+            },
+            Stmt::Print { expression, .. } => {
+                self.parenthesize(String::from("print"), &[expression])
+            },
+            Stmt::Return { value, .. } => {
+                match value {
+                    Some(value) => self.parenthesize(String::from("return"), &[value]),
+                    None => String::from("(return)"),
+                }
+            },
+            Stmt::Var { name, initializer, .. } => {
+                match initializer.as_ref() {
+                    Expr::Literal { value, .. } if matches!(value.as_ref(), Object::Unitialized) => {
+                        self.parenthesize_parts("var", &[name.lexeme.to_owned()])
+                    },
+                    _ => {
+                        let initializer = self.print(initializer);
+                        self.parenthesize_parts("var", &[name.lexeme.to_owned(), String::from("="), initializer])
+                    },
+                }
+            },
+            Stmt::While { condition, body, increment, .. } => {
+                let mut parts = vec![self.print(condition), self.print_stmt(body)];
+
+                if let Some(increment) = increment {
+                    parts.push(self.print(increment));
+                }
+
+                self.parenthesize_parts("while", &parts)
+            },
+        }
+    }
+}
@@ -1,3 +1,7 @@
+use crate::unicode_tables::{XID_CONTINUE, XID_START};
+
+/// Identifier rules follow Unicode `XID_Start` and `XID_Continue`, with `_`
+/// also allowed to start an identifier.
 pub trait CharExtensions {
     fn is_alpha_numeric(&self) -> bool;
     fn is_alpha(&self) -> bool;
@@ -5,12 +9,30 @@ pub trait CharExtensions {
 
 impl CharExtensions for char {
     fn is_alpha_numeric(&self) -> bool {
-        self.is_alpha() || self.is_digit(10)
+        if self.is_ascii() {
+            return self.is_ascii_alphanumeric() || self == &'_';
+        }
+
+        in_table(*self, XID_CONTINUE)
     }
 
     fn is_alpha(&self) -> bool {
-        self >= &'a' && self <= &'z' ||
-        self >= &'A' && self <= &'Z' ||
-        self == &'_'
+        if self.is_ascii() {
+            return self.is_ascii_alphabetic() || self == &'_';
+        }
+
+        in_table(*self, XID_START)
     }
-}
\ No newline at end of file
+}
+
+fn in_table(c: char, table: &[(char, char)]) -> bool {
+    table.binary_search_by(|&(start, end)| {
+        if end < c {
+            std::cmp::Ordering::Less
+        } else if start > c {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    }).is_ok()
+}
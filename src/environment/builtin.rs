@@ -1,10 +1,140 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+// Every builtin takes the boxed argument slice of `BuiltinSignature`.
+#![allow(clippy::boxed_local)]
+
+use std::{cell::RefCell, rc::Rc, time::{SystemTime, UNIX_EPOCH}};
 
 use crate::environment::Object;
 
-use super::{Args, BObject};
+use super::{heap::Heap, map::{Map, MapKey}, Args, BObject, List};
 
-pub fn clock(_: Args) -> BObject {
+pub fn clock(_: Args) -> Result<BObject, String> {
     let time = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards");
-    Box::new(Object::Number(time.as_millis() as f64))
-}
\ No newline at end of file
+    Ok(Box::new(Object::Number(time.as_millis() as f64)))
+}
+
+fn list(function: &str, value: &Object) -> Result<List, String> {
+    match value {
+        Object::List(elements) => Ok(elements.clone()),
+        _ => Err(format!("{}() expects a list, but got {}.", function, value)),
+    }
+}
+
+fn map(function: &str, value: &Object) -> Result<Rc<RefCell<Map>>, String> {
+    match value {
+        Object::Map(map) => Ok(map.clone()),
+        _ => Err(format!("{}() expects a map, but got {}.", function, value)),
+    }
+}
+
+fn new_list(elements: Vec<BObject>) -> BObject {
+    Box::new(Object::List(Heap::list(elements)))
+}
+
+/// Position in a list of `len` elements where a new element can go,
+/// including one past the end.
+fn insertion_index(function: &str, index: &Object, len: usize) -> Result<usize, String> {
+    match index {
+        Object::Number(n) if *n == len as f64 => Ok(len),
+        _ => Object::list_index(index, len).map_err(|message| format!("{}(): {}", function, message)),
+    }
+}
+
+pub fn len(args: Args) -> Result<BObject, String> {
+    let len = match args[0].as_ref() {
+        Object::List(elements) => elements.borrow().len(),
+        Object::Map(map) => map.borrow().len(),
+        Object::String(s) => s.chars().count(),
+        other => return Err(format!("len() expects a list, a map or a string, but got {}.", other)),
+    };
+
+    Ok(Box::new(Object::Number(len as f64)))
+}
+
+pub fn push(args: Args) -> Result<BObject, String> {
+    let elements = list("push", &args[0])?;
+    elements.borrow_mut().push(args[1].to_owned());
+    Ok(Box::new(Object::Nil))
+}
+
+pub fn pop(args: Args) -> Result<BObject, String> {
+    let elements = list("pop", &args[0])?;
+    let value = elements.borrow_mut().pop();
+    value.ok_or_else(|| String::from("pop() from an empty list."))
+}
+
+pub fn insert(args: Args) -> Result<BObject, String> {
+    let elements = list("insert", &args[0])?;
+    let index = insertion_index("insert", &args[1], elements.borrow().len())?;
+    elements.borrow_mut().insert(index, args[2].to_owned());
+    Ok(Box::new(Object::Nil))
+}
+
+pub fn remove(args: Args) -> Result<BObject, String> {
+    let elements = list("remove", &args[0])?;
+    let index = Object::list_index(&args[1], elements.borrow().len())
+        .map_err(|message| format!("remove(): {}", message))?;
+    let value = elements.borrow_mut().remove(index);
+    Ok(value)
+}
+
+/// New list with the elements from `start` up to, but not including, `end`.
+pub fn slice(args: Args) -> Result<BObject, String> {
+    let elements = list("slice", &args[0])?;
+    let elements = elements.borrow();
+
+    let start = insertion_index("slice", &args[1], elements.len())?;
+    let end = insertion_index("slice", &args[2], elements.len())?;
+
+    if start > end {
+        return Err(format!("slice() start {} is past its end {}.", start, end));
+    }
+
+    Ok(new_list(elements[start..end].to_vec()))
+}
+
+/// Keys of a map as a list, in insertion order.
+pub fn keys(args: Args) -> Result<BObject, String> {
+    let map = map("keys", &args[0])?;
+    let keys = map.borrow().keys().map(|key| Box::new(key.to_object())).collect();
+    Ok(new_list(keys))
+}
+
+/// Values of a map as a list, in insertion order.
+pub fn values(args: Args) -> Result<BObject, String> {
+    let map = map("values", &args[0])?;
+    let values = map.borrow().values().cloned().collect();
+    Ok(new_list(values))
+}
+
+pub fn has(args: Args) -> Result<BObject, String> {
+    let map = map("has", &args[0])?;
+    let key = MapKey::new(&args[1])?;
+    let has = map.borrow().contains(&key);
+    Ok(Box::new(Object::Boolean(has)))
+}
+
+/// Removes a key from a map and returns its value, or nil if it was missing.
+pub fn delete(args: Args) -> Result<BObject, String> {
+    let map = map("delete", &args[0])?;
+    let key = MapKey::new(&args[1])?;
+    let value = map.borrow_mut().remove(&key);
+    Ok(value.unwrap_or_else(|| Box::new(Object::Nil)))
+}
+
+/// Garbage collector counters, as a map.
+pub fn gc_stats(_: Args) -> Result<BObject, String> {
+    let stats = Heap::stats();
+    let mut map = Map::new();
+
+    for (name, value) in [
+        ("collections", stats.collections),
+        ("liveBytes", stats.live_bytes),
+        ("liveObjects", stats.live_objects),
+        ("freedObjects", stats.freed_objects),
+        ("nextCollection", stats.next_collection),
+    ] {
+        map.insert(MapKey::String(name.to_owned()), Box::new(Object::Number(value as f64)));
+    }
+
+    Ok(Box::new(Object::Map(Heap::map(map))))
+}
@@ -0,0 +1,109 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+
+use crate::{error::RuntimeError, token::{Token, TokenType}};
+
+use super::{environment::Environment, heap::Heap, BObject, Object, ObjectCaller};
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    pub methods: HashMap<String, BObject>,
+}
+
+impl Class {
+    pub fn new(name: String, superclass: Option<Rc<Class>>, methods: HashMap<String, BObject>) -> Self {
+        Self { name, superclass, methods }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<BObject> {
+        match self.methods.get(name) {
+            Some(method) => Some(method.to_owned()),
+            None => self.superclass.as_ref().and_then(|superclass| superclass.find_method(name)),
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => 0,
+        }
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Rc<Class>,
+    fields: HashMap<String, BObject>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self { class, fields: HashMap::new() }
+    }
+
+    pub fn set(&mut self, name: &Token, value: BObject) {
+        self.set_field(&name.lexeme, value);
+    }
+
+    pub fn field(&self, name: &str) -> Option<&BObject> {
+        self.fields.get(name)
+    }
+
+    pub fn set_field(&mut self, name: &str, value: BObject) {
+        self.fields.insert(name.to_owned(), value);
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (&String, &BObject)> {
+        self.fields.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.fields.clear();
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
+/// Looks up a property on an instance. Fields shadow methods, and
+/// methods are bound to the instance they were accessed through.
+pub fn get_property(instance: &Rc<RefCell<Instance>>, name: &Token) -> Result<BObject, RuntimeError> {
+    if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+        return Ok(value.to_owned());
+    }
+
+    let class = instance.borrow().class.clone();
+    match class.find_method(&name.lexeme) {
+        Some(method) => bind(&method, Box::new(Object::Instance(instance.clone()))),
+        None => Err(RuntimeError::new(name, format!("Undefined property '{}'.", name.lexeme))),
+    }
+}
+
+/// Wraps a method in a new environment where `this` refers to `instance`.
+pub fn bind(method: &Object, instance: BObject) -> Result<BObject, RuntimeError> {
+    match method {
+        Object::Function { name, params, body, environment, is_initializer } => {
+            let mut env = Environment::new_enclosing(environment.clone());
+            env.define(&Token::with_lexeme(String::from("this"), TokenType::This), instance)?;
+
+            Ok(Box::new(Object::Function {
+                name: name.to_owned(),
+                params: params.to_owned(),
+                body: body.to_owned(),
+                environment: Heap::environment(env),
+                is_initializer: *is_initializer,
+            }))
+        },
+        other => Ok(Box::new(other.to_owned())),
+    }
+}
@@ -1,36 +1,57 @@
 use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 pub type MutEnv = Rc<RefCell<Environment>>;
-use crate::{environment::{BuiltinSignature, Object}, error::ErrorHandler, token::{Token, TokenType}};
+use crate::{environment::{BuiltinSignature, Object}, error::RuntimeError, token::Token};
 
-use super::{builtin::clock, BObject};
+use super::{builtin, BObject};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Environment{
     pub values: HashMap<String, BObject>,
     enclosing: Option<MutEnv>,
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     fn define_builtins(&mut self) {
-        self.define_builtin("clock", clock);
+        self.define_builtin("clock", 0, builtin::clock);
+        self.define_builtin("len", 1, builtin::len);
+        self.define_builtin("push", 2, builtin::push);
+        self.define_builtin("pop", 1, builtin::pop);
+        self.define_builtin("insert", 3, builtin::insert);
+        self.define_builtin("remove", 2, builtin::remove);
+        self.define_builtin("slice", 3, builtin::slice);
+        self.define_builtin("keys", 1, builtin::keys);
+        self.define_builtin("values", 1, builtin::values);
+        self.define_builtin("has", 2, builtin::has);
+        self.define_builtin("delete", 2, builtin::delete);
+        self.define_builtin("gcStats", 0, builtin::gc_stats);
     }
 
-    fn define_builtin(&mut self, identificator: &'static str, signature: BuiltinSignature){
-        self.define(
-            &Token::with_lexeme(identificator.to_owned(), TokenType::Identifier), 
-            Box::new(Object::Builtin(identificator.to_owned(), signature))
-        )
+    pub fn define_builtin(&mut self, identificator: &str, arity: usize, signature: BuiltinSignature){
+        self.values.insert(
+            identificator.to_owned(),
+            Box::new(Object::Builtin(identificator.to_owned(), arity, signature))
+        );
     }
 
     pub fn new() -> Self {
-        let mut env = Environment{
+        Environment{
             values: HashMap::new(), 
             enclosing: None
-        };
+        }
+    }
 
+    /// Scope holding the builtins, enclosing the globals so that scripts
+    /// can declare globals with the same names.
+    pub fn prelude() -> Self {
+        let mut env = Self::new();
         env.define_builtins();
-
         env
     }
 
@@ -39,57 +60,85 @@ impl Environment {
             enclosing: Some(enclosing)}
     }
 
-    pub fn define(&mut self, name: &Token, value: BObject) {
+    pub fn enclosing(&self) -> Option<&MutEnv> {
+        self.enclosing.as_ref()
+    }
+
+    /// Drops every variable and the enclosing scope.
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.enclosing = None;
+    }
+
+    pub fn define(&mut self, name: &Token, value: BObject) -> Result<(), RuntimeError> {
         let lexeme = name.lexeme.to_owned();
 
         if self.values.contains_key(&lexeme){
-            ErrorHandler::runtime_error(name, format!("Variable '{}' already defined.", lexeme));
+            return Err(RuntimeError::new(name, format!("Variable '{}' already defined.", lexeme)));
         }
 
-        self.values.insert(lexeme.clone(), value);
+        self.values.insert(lexeme, value);
+        Ok(())
     }
 
-    pub fn assign(&mut self, name: &Token, value: BObject) {
+    pub fn assign(&mut self, name: &Token, value: BObject) -> Result<(), RuntimeError> {
         let lexeme = name.lexeme.to_owned();
 
-        if self.values.contains_key(&lexeme){
-            self.values.insert(lexeme, value.to_owned());
-            return;
+        if let Some(current) = self.values.get_mut(&lexeme){
+            *current = value;
+            return Ok(());
         }
 
         match self.enclosing.as_deref() {
             Some(enclosing ) => {
-                enclosing.borrow_mut().assign(name, value.to_owned());
+                enclosing.borrow_mut().assign(name, value)
             },
             None => {
-                ErrorHandler::runtime_error(name, format!("Undefined variable '{}'.", lexeme));
+                Err(RuntimeError::new(name, format!("Undefined variable '{}'.", lexeme)))
             },
         }
     }
 
-    pub fn get(&self, name: Token) -> BObject {
+    fn ancestor(environment: &MutEnv, distance: usize) -> MutEnv {
+        let mut environment = environment.clone();
+
+        for _ in 0..distance {
+            let enclosing = environment.borrow().enclosing.clone()
+                .expect("Resolved scope depth exceeds environment chain.");
+            environment = enclosing;
+        }
+
+        environment
+    }
+
+    pub fn get_at(environment: &MutEnv, distance: usize, name: Token) -> Result<BObject, RuntimeError> {
+        Self::ancestor(environment, distance).borrow().get(name)
+    }
+
+    pub fn assign_at(environment: &MutEnv, distance: usize, name: &Token, value: BObject) -> Result<(), RuntimeError> {
+        Self::ancestor(environment, distance).borrow_mut().assign(name, value)
+    }
+
+    pub fn get(&self, name: Token) -> Result<BObject, RuntimeError> {
         let key = name.lexeme.to_owned();
 
         if self.values.contains_key(&key){
             let value = self.values.get(&key).unwrap();
 
             if value.is_equal(Object::Unitialized){
-                ErrorHandler::runtime_error(&name, format!("Variable '{}' has not been initialized or assigned to.", key));
-                return Box::new(Object::Unitialized)
+                return Err(RuntimeError::new(&name, format!("Variable '{}' has not been initialized or assigned to.", key)));
             }
 
-            return value.to_owned();
+            return Ok(value.to_owned());
         } 
 
-        let value = match self.enclosing.as_deref(){
+        match self.enclosing.as_deref(){
             Some(enclosing ) => {
                 enclosing.borrow().get(name)
             },
             None => {
-                ErrorHandler::runtime_error(&name, format!("Undefined variable '{}'.", key));
-                Box::new(Object::Nil)
+                Err(RuntimeError::new(&name, format!("Undefined variable '{}'.", key)))
             },
-        };
-        return value;
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,406 @@
+use std::{cell::RefCell, collections::HashMap, mem::size_of, rc::{Rc, Weak}};
+
+use crate::vm::function::{BoundMethod, Closure, Upvalue};
+
+use super::{class::{Class, Instance}, environment::{Environment, MutEnv}, map::{Map, MapKey}, BObject, List, Object};
+
+thread_local! {
+    static HEAP: RefCell<Heap> = RefCell::new(Heap::new());
+}
+
+/// When the collector runs: once the estimated heap size passes
+/// `initial_threshold`, and afterwards once it grows to `growth_factor`
+/// times what survived the previous collection.
+#[derive(Debug, Clone, Copy)]
+pub struct GcConfig {
+    pub initial_threshold: usize,
+    pub growth_factor: usize,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self { initial_threshold: 1024 * 1024, growth_factor: 2 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcStats {
+    pub collections: usize,
+    /// Estimated size of the tracked objects `Rc` has not freed yet,
+    /// including unreachable cycles awaiting the next collection.
+    pub live_bytes: usize,
+    pub live_objects: usize,
+    pub freed_objects: usize,
+    pub next_collection: usize,
+}
+
+/// Registry of the mutable objects scripts allocate: environments, lists,
+/// maps, instances and upvalues. Values still reference them through `Rc`,
+/// and the collector frees the reference cycles `Rc` alone would leak.
+///
+/// Collection is a mark-and-sweep over every object reachable from the
+/// registry. Roots are the objects referenced from outside the heap, which
+/// covers the globals, the active environments and call stack and any
+/// value held by an expression being evaluated: an object with more strong
+/// references than the heap accounts for is referenced from elsewhere.
+/// Unreachable objects are cleared, which breaks their cycles and lets
+/// `Rc` free them.
+pub struct Heap {
+    tracked: Vec<Tracked>,
+    config: GcConfig,
+    /// Estimated size of what survived the last collection plus everything
+    /// allocated since, compared against `next_collection`.
+    bytes_allocated: usize,
+    next_collection: usize,
+    collections: usize,
+    freed_objects: usize,
+}
+
+impl Heap {
+    fn new() -> Self {
+        let config = GcConfig::default();
+
+        Self {
+            tracked: Vec::new(),
+            config,
+            bytes_allocated: 0,
+            next_collection: config.initial_threshold,
+            collections: 0,
+            freed_objects: 0,
+        }
+    }
+
+    pub fn environment(environment: Environment) -> MutEnv {
+        let environment = Rc::new(RefCell::new(environment));
+        Self::track(Node::Environment(environment.clone()));
+        environment
+    }
+
+    pub fn list(elements: Vec<BObject>) -> List {
+        let list = Rc::new(RefCell::new(elements));
+        Self::track(Node::List(list.clone()));
+        list
+    }
+
+    pub fn map(map: Map) -> Rc<RefCell<Map>> {
+        let map = Rc::new(RefCell::new(map));
+        Self::track(Node::Map(map.clone()));
+        map
+    }
+
+    pub fn instance(instance: Instance) -> Rc<RefCell<Instance>> {
+        let instance = Rc::new(RefCell::new(instance));
+        Self::track(Node::Instance(instance.clone()));
+        instance
+    }
+
+    pub fn upvalue(upvalue: Upvalue) -> Rc<RefCell<Upvalue>> {
+        let upvalue = Rc::new(RefCell::new(upvalue));
+        Self::track(Node::Upvalue(upvalue.clone()));
+        upvalue
+    }
+
+    /// Registers a new object, then collects if the heap has grown past the
+    /// threshold. Objects borrowed by the caller at this point are
+    /// treated as roots, so collecting in the middle of an operation is safe.
+    fn track(node: Node) {
+        HEAP.with(|heap| {
+            let mut heap = heap.borrow_mut();
+            heap.bytes_allocated += node.size();
+            heap.tracked.extend(node.downgrade());
+        });
+
+        drop(node);
+        Self::collect_if_needed();
+    }
+
+    /// Sets the collection thresholds, starting over from the initial one.
+    pub fn configure(config: GcConfig) {
+        HEAP.with(|heap| {
+            let mut heap = heap.borrow_mut();
+            heap.config = config;
+            heap.next_collection = config.initial_threshold;
+        });
+    }
+
+    fn collect_if_needed() {
+        let needed = HEAP.with(|heap| {
+            let heap = heap.borrow();
+            heap.bytes_allocated > heap.next_collection
+        });
+
+        if needed {
+            Self::collect();
+        }
+    }
+
+    pub fn collect() {
+        HEAP.with(|heap| heap.borrow_mut().mark_and_sweep());
+    }
+
+    pub fn stats() -> GcStats {
+        HEAP.with(|heap| {
+            let heap = heap.borrow();
+            let live: Vec<Node> = heap.tracked.iter().filter_map(|tracked| tracked.upgrade()).collect();
+
+            GcStats {
+                collections: heap.collections,
+                live_bytes: live.iter().map(|node| node.size()).sum(),
+                live_objects: live.len(),
+                freed_objects: heap.freed_objects,
+                next_collection: heap.next_collection,
+            }
+        })
+    }
+
+    fn mark_and_sweep(&mut self) {
+        let mut nodes: Vec<Node> = self.tracked.drain(..)
+            .filter_map(|tracked| tracked.upgrade())
+            .collect();
+        let mut indices: HashMap<*const (), usize> = nodes.iter().enumerate()
+            .map(|(index, node)| (node.address(), index))
+            .collect();
+
+        // Discover everything the tracked objects reference. The collector
+        // keeps exactly one reference to each object it finds.
+        let mut edges: Vec<Vec<usize>> = Vec::new();
+        let mut roots = Vec::new();
+        let mut index = 0;
+
+        while index < nodes.len() {
+            let Some(children) = nodes[index].children() else {
+                // Borrowed right now, so something outside the heap is using it.
+                roots.push(index);
+                edges.push(Vec::new());
+                index += 1;
+                continue;
+            };
+
+            let mut targets = Vec::new();
+            for child in children {
+                let target = *indices.entry(child.address()).or_insert_with(|| {
+                    nodes.push(child);
+                    nodes.len() - 1
+                });
+                targets.push(target);
+            }
+
+            edges.push(targets);
+            index += 1;
+        }
+
+        let mut internal = vec![0; nodes.len()];
+        for target in edges.iter().flatten() {
+            internal[*target] += 1;
+        }
+
+        roots.extend((0..nodes.len()).filter(|index| nodes[*index].strong_count() - 1 > internal[*index]));
+
+        let mut reachable = vec![false; nodes.len()];
+        while let Some(index) = roots.pop() {
+            if !reachable[index] {
+                reachable[index] = true;
+                roots.extend(edges[index].iter().filter(|target| !reachable[**target]));
+            }
+        }
+
+        let mut live_bytes = 0;
+
+        for (node, reachable) in nodes.iter().zip(reachable) {
+            if reachable {
+                live_bytes += node.size();
+                self.tracked.extend(node.downgrade());
+            } else {
+                node.clear();
+                self.freed_objects += 1;
+            }
+        }
+
+        self.collections += 1;
+        self.bytes_allocated = live_bytes;
+        self.next_collection = (live_bytes * self.config.growth_factor).max(self.config.initial_threshold);
+    }
+}
+
+/// Weak handle to an object in the registry.
+enum Tracked {
+    Environment(Weak<RefCell<Environment>>),
+    List(Weak<RefCell<Vec<BObject>>>),
+    Map(Weak<RefCell<Map>>),
+    Instance(Weak<RefCell<Instance>>),
+    Upvalue(Weak<RefCell<Upvalue>>),
+}
+
+impl Tracked {
+    fn upgrade(&self) -> Option<Node> {
+        match self {
+            Tracked::Environment(weak) => weak.upgrade().map(Node::Environment),
+            Tracked::List(weak) => weak.upgrade().map(Node::List),
+            Tracked::Map(weak) => weak.upgrade().map(Node::Map),
+            Tracked::Instance(weak) => weak.upgrade().map(Node::Instance),
+            Tracked::Upvalue(weak) => weak.upgrade().map(Node::Upvalue),
+        }
+    }
+}
+
+/// Heap object that can reference other objects.
+enum Node {
+    Environment(MutEnv),
+    List(List),
+    Map(Rc<RefCell<Map>>),
+    Instance(Rc<RefCell<Instance>>),
+    Upvalue(Rc<RefCell<Upvalue>>),
+    Class(Rc<Class>),
+    Closure(Rc<Closure>),
+    BoundMethod(Rc<BoundMethod>),
+}
+
+impl Node {
+    fn address(&self) -> *const () {
+        match self {
+            Node::Environment(rc) => Rc::as_ptr(rc) as *const (),
+            Node::List(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Map(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Instance(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Upvalue(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Class(rc) => Rc::as_ptr(rc) as *const (),
+            Node::Closure(rc) => Rc::as_ptr(rc) as *const (),
+            Node::BoundMethod(rc) => Rc::as_ptr(rc) as *const (),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Environment(rc) => Rc::strong_count(rc),
+            Node::List(rc) => Rc::strong_count(rc),
+            Node::Map(rc) => Rc::strong_count(rc),
+            Node::Instance(rc) => Rc::strong_count(rc),
+            Node::Upvalue(rc) => Rc::strong_count(rc),
+            Node::Class(rc) => Rc::strong_count(rc),
+            Node::Closure(rc) => Rc::strong_count(rc),
+            Node::BoundMethod(rc) => Rc::strong_count(rc),
+        }
+    }
+
+    fn downgrade(&self) -> Option<Tracked> {
+        match self {
+            Node::Environment(rc) => Some(Tracked::Environment(Rc::downgrade(rc))),
+            Node::List(rc) => Some(Tracked::List(Rc::downgrade(rc))),
+            Node::Map(rc) => Some(Tracked::Map(Rc::downgrade(rc))),
+            Node::Instance(rc) => Some(Tracked::Instance(Rc::downgrade(rc))),
+            Node::Upvalue(rc) => Some(Tracked::Upvalue(Rc::downgrade(rc))),
+            Node::Class(_) | Node::Closure(_) | Node::BoundMethod(_) => None,
+        }
+    }
+
+    /// Objects this one references, or `None` if it is mutably borrowed.
+    fn children(&self) -> Option<Vec<Node>> {
+        let mut children = Vec::new();
+
+        match self {
+            Node::Environment(environment) => {
+                let environment = environment.try_borrow().ok()?;
+                environment.values.values().for_each(|value| value.trace(&mut children));
+                children.extend(environment.enclosing().cloned().map(Node::Environment));
+            },
+            Node::List(list) => list.try_borrow().ok()?.iter().for_each(|value| value.trace(&mut children)),
+            Node::Map(map) => map.try_borrow().ok()?.values().for_each(|value| value.trace(&mut children)),
+            Node::Instance(instance) => {
+                let instance = instance.try_borrow().ok()?;
+                children.push(Node::Class(instance.class.clone()));
+                instance.fields().for_each(|(_, value)| value.trace(&mut children));
+            },
+            Node::Upvalue(upvalue) => {
+                if let Upvalue::Closed(value) = &*upvalue.try_borrow().ok()? {
+                    value.trace(&mut children);
+                }
+            },
+            Node::Class(class) => {
+                children.extend(class.superclass.clone().map(Node::Class));
+                class.methods.values().for_each(|method| method.trace(&mut children));
+            },
+            Node::Closure(closure) => children.extend(closure.upvalues.iter().cloned().map(Node::Upvalue)),
+            Node::BoundMethod(bound) => {
+                bound.receiver.trace(&mut children);
+                children.push(Node::Closure(bound.method.clone()));
+            },
+        }
+
+        Some(children)
+    }
+
+    /// Drops everything an unreachable object references.
+    fn clear(&self) {
+        match self {
+            Node::Environment(environment) => {
+                if let Ok(mut environment) = environment.try_borrow_mut() {
+                    environment.clear();
+                }
+            },
+            Node::List(list) => {
+                if let Ok(mut list) = list.try_borrow_mut() {
+                    list.clear();
+                }
+            },
+            Node::Map(map) => {
+                if let Ok(mut map) = map.try_borrow_mut() {
+                    map.clear();
+                }
+            },
+            Node::Instance(instance) => {
+                if let Ok(mut instance) = instance.try_borrow_mut() {
+                    instance.clear();
+                }
+            },
+            Node::Upvalue(upvalue) => {
+                if let Ok(mut upvalue) = upvalue.try_borrow_mut() {
+                    *upvalue = Upvalue::Closed(Object::Nil);
+                }
+            },
+            // Immutable, so never part of a cycle on their own; clearing
+            // the mutable objects around them breaks it.
+            Node::Class(_) | Node::Closure(_) | Node::BoundMethod(_) => {},
+        }
+    }
+
+    /// Estimated number of bytes the object occupies.
+    fn size(&self) -> usize {
+        const VALUE: usize = size_of::<BObject>() + size_of::<Object>();
+
+        match self {
+            Node::Environment(environment) => environment.try_borrow().map_or(0, |environment| {
+                let names: usize = environment.values.keys().map(|name| name.len()).sum();
+                size_of::<Environment>() + environment.values.len() * (size_of::<String>() + VALUE) + names
+            }),
+            Node::List(list) => list.try_borrow().map_or(0, |list| size_of::<Vec<BObject>>() + list.len() * VALUE),
+            Node::Map(map) => map.try_borrow().map_or(0, |map| {
+                size_of::<Map>() + map.len() * (2 * size_of::<MapKey>() + size_of::<usize>() + VALUE)
+            }),
+            Node::Instance(instance) => instance.try_borrow().map_or(0, |instance| {
+                let names: usize = instance.fields().map(|(name, _)| name.len()).sum();
+                size_of::<Instance>() + instance.fields().count() * (size_of::<String>() + VALUE) + names
+            }),
+            Node::Upvalue(_) => size_of::<Upvalue>(),
+            Node::Class(class) => size_of::<Class>() + class.methods.len() * (size_of::<String>() + VALUE),
+            Node::Closure(closure) => size_of::<Closure>() + closure.upvalues.len() * size_of::<Rc<RefCell<Upvalue>>>(),
+            Node::BoundMethod(_) => size_of::<BoundMethod>(),
+        }
+    }
+}
+
+impl Object {
+    /// Collects the heap objects this value references.
+    fn trace(&self, children: &mut Vec<Node>) {
+        match self {
+            Object::Function{environment, ..} => children.push(Node::Environment(environment.clone())),
+            Object::Class(class) => children.push(Node::Class(class.clone())),
+            Object::Instance(instance) => children.push(Node::Instance(instance.clone())),
+            Object::List(list) => children.push(Node::List(list.clone())),
+            Object::Map(map) => children.push(Node::Map(map.clone())),
+            Object::Closure(closure) => children.push(Node::Closure(closure.clone())),
+            Object::BoundMethod(bound) => children.push(Node::BoundMethod(bound.clone())),
+            Object::Number(_) | Object::Boolean(_) | Object::String(_) | Object::Nil
+            | Object::Unitialized | Object::Builtin(..) => {},
+        }
+    }
+}
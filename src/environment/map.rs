@@ -0,0 +1,118 @@
+use std::{collections::HashMap, fmt::Display};
+
+use super::{csv_str, BObject, Object};
+
+/// Value usable as a map key. Numbers are compared by their bits, with
+/// `-0` folded into `0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    String(String),
+    Number(u64),
+    Boolean(bool),
+    Nil,
+}
+
+impl MapKey {
+    pub fn new(value: &Object) -> Result<Self, String> {
+        match value {
+            Object::String(s) => Ok(MapKey::String(s.to_owned())),
+            Object::Number(n) if *n == 0.0 => Ok(MapKey::Number(0f64.to_bits())),
+            Object::Number(n) => Ok(MapKey::Number(n.to_bits())),
+            Object::Boolean(b) => Ok(MapKey::Boolean(*b)),
+            Object::Nil => Ok(MapKey::Nil),
+            other => Err(format!("Map keys must be strings, numbers, booleans or nil, but got {}.", other)),
+        }
+    }
+
+    pub fn to_object(&self) -> Object {
+        match self {
+            MapKey::String(s) => Object::String(s.to_owned()),
+            MapKey::Number(bits) => Object::Number(f64::from_bits(*bits)),
+            MapKey::Boolean(b) => Object::Boolean(*b),
+            MapKey::Nil => Object::Nil,
+        }
+    }
+}
+
+impl Display for MapKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_object())
+    }
+}
+
+/// Hash map that remembers insertion order, so printing and iterating a
+/// map is deterministic.
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(MapKey, BObject)>,
+    positions: HashMap<MapKey, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&BObject> {
+        self.positions.get(key).map(|position| &self.entries[*position].1)
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    /// Sets the value of `key`, keeping its place if it is already present.
+    pub fn insert(&mut self, key: MapKey, value: BObject) {
+        match self.positions.get(&key) {
+            Some(position) => self.entries[*position].1 = value,
+            None => {
+                self.positions.insert(key.to_owned(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<BObject> {
+        let position = self.positions.remove(key)?;
+        let (_, value) = self.entries.remove(position);
+
+        for (key, _) in self.entries[position..].iter() {
+            if let Some(later) = self.positions.get_mut(key) {
+                *later -= 1;
+            }
+        }
+
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.positions.clear();
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &MapKey> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &BObject> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self.entries.iter()
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect::<Vec<String>>();
+
+        write!(f, "{{{}}}", csv_str(&entries))
+    }
+}
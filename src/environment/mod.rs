@@ -1,32 +1,50 @@
-mod builtin;
+pub mod builtin;
+pub mod class;
+#[allow(clippy::module_inception)]
 pub mod environment;
+pub mod heap;
+pub mod map;
 
-use std::{cell::RefCell, fmt::Display, panic, rc::Rc};
+use std::{cell::RefCell, fmt::Display, rc::Rc};
 
+use class::{Class, Instance};
 use environment::{Environment, MutEnv};
+use heap::Heap;
+use map::{Map, MapKey};
 
-use crate::{expression::Expr, interpreter::{self, Interpreter}, returner::Return, statement::Stmt, token::Token};
+use crate::{error::RuntimeError, interpreter::Interpreter, returner::Unwind, statement::Stmt, token::{Token, TokenType}, vm::function::{BoundMethod, Closure}};
 
 pub type BObject = Box<Object>;
-pub type BuiltinSignature = fn(Box<[BObject]>) -> BObject;
+/// Native function. An `Err` message becomes a runtime error at the call.
+pub type BuiltinSignature = fn(Box<[BObject]>) -> Result<BObject, String>;
 pub type Args = Box<[BObject]>;
+/// Elements of a list, shared by every value referring to it.
+pub type List = Rc<RefCell<Vec<BObject>>>;
 
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Object {
     Number(f64),
     Boolean(bool),
     String(String),
     Nil,
     Unitialized,
-    Return(BObject),
     Function{
         name: Box<Token>,
         params: Box<[Token]>,
         body: Box<[Box<Stmt>]>,
-        environment: MutEnv
+        environment: MutEnv,
+        is_initializer: bool
     },
-    Builtin(String, BuiltinSignature),
+    Builtin(String, usize, BuiltinSignature),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    List(List),
+    Map(Rc<RefCell<Map>>),
+    /// Compiled function with its captured variables, run by the bytecode VM.
+    Closure(Rc<Closure>),
+    /// Method bound to the instance it was accessed through, in the bytecode VM.
+    BoundMethod(Rc<BoundMethod>),
 }
 
 pub fn csv_str<T: Display>(arr: &[T]) -> String {
@@ -37,28 +55,165 @@ pub fn csv_str<T: Display>(arr: &[T]) -> String {
 }
 
 impl Object {
-    pub fn is_thuthy(self) -> bool {
+    pub fn is_thuthy(&self) -> bool {
         match self {
             Object::Nil | Object::Unitialized => false,
-            Object::Boolean(v) => v.clone(),
+            Object::Boolean(v) => *v,
             _ => true,
         }
     }
 
+    /// Name used for this value when it appears in a stack trace.
+    pub fn callable_name(&self) -> String {
+        match self {
+            Object::Function{name, ..} => name.lexeme.to_owned(),
+            Object::Builtin(name, ..) => name.to_owned(),
+            Object::Class(class) => class.name.to_owned(),
+            Object::Closure(closure) => closure.function.name.to_owned(),
+            Object::BoundMethod(bound) => bound.method.function.name.to_owned(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Checks that `index` is a whole number addressing one of `len` elements.
+    pub fn list_index(index: &Object, len: usize) -> Result<usize, String> {
+        match index {
+            Object::Number(n) if n.fract() == 0.0 && *n >= 0.0 && (*n as usize) < len => Ok(*n as usize),
+            Object::Number(n) if n.fract() == 0.0 => Err(format!("Index {} out of bounds for list of length {}.", n, len)),
+            _ => Err(String::from("List index must be a whole number.")),
+        }
+    }
+
+    /// Applies an arithmetic, comparison or equality operator.
+    pub fn binary(left: Object, operator: &TokenType, right: Object) -> Result<Object, String> {
+        let value = match (left, right) {
+            (Object::String(str1), Object::String(str2)) => {
+                match operator {
+                    TokenType::Plus => Object::String(str1 + &str2),
+                    TokenType::Slash | TokenType::Star | TokenType::Minus => return Err(String::from("Operands must be numbers.")),
+                    TokenType::BangEqual => Object::Boolean(str1 != str2),
+                    TokenType::EqualEqual => Object::Boolean(str1 == str2),
+                    _ => Object::Nil
+                }
+            },
+            (Object::Number(num1), Object::Number(num2)) => {
+                match operator {
+                    TokenType::Plus => Object::Number(num1 + num2),
+                    TokenType::Minus => Object::Number(num1 - num2),
+                    TokenType::Slash => Object::Number(num1 / num2),
+                    TokenType::Star => Object::Number(num1 * num2),
+                    TokenType::Greater => Object::Boolean(num1 > num2),
+                    TokenType::GreaterEqual => Object::Boolean(num1 >= num2),
+                    TokenType::Less => Object::Boolean(num1 < num2),
+                    TokenType::LessEqual => Object::Boolean(num1 <= num2),
+                    TokenType::BangEqual => Object::Boolean(num1 != num2),
+                    TokenType::EqualEqual => Object::Boolean(num1 == num2),
+                    _ => Object::Number(0.0)
+                }
+            },
+            (val1, val2) => {
+                match operator {
+                    TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual |
+                    TokenType::Slash | TokenType::Star | TokenType::Minus => return Err(String::from("Operands must be numbers.")),
+                    TokenType::Plus => return Err(String::from("Operands must be two numbers or two strings.")),
+                    TokenType::BangEqual => Object::Boolean(!val1.is_equal(val2)),
+                    TokenType::EqualEqual => Object::Boolean(val1.is_equal(val2)),
+                    _ => Object::Nil
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Reads `self[index]` from a list or a map.
+    pub fn get_index(&self, index: &Object) -> Result<BObject, String> {
+        match self {
+            Object::List(elements) => {
+                let elements = elements.borrow();
+                let index = Object::list_index(index, elements.len())?;
+                Ok(elements[index].to_owned())
+            },
+            Object::Map(map) => {
+                let key = MapKey::new(index)?;
+
+                match map.borrow().get(&key) {
+                    Some(value) => Ok(value.to_owned()),
+                    None => Err(format!("Undefined key '{}'.", key)),
+                }
+            },
+            _ => Err(String::from("Only lists and maps can be indexed.")),
+        }
+    }
+
+    /// Stores `value` at `self[index]` in a list or a map.
+    pub fn set_index(&self, index: &Object, value: BObject) -> Result<(), String> {
+        match self {
+            Object::List(elements) => {
+                let mut elements = elements.borrow_mut();
+                let index = Object::list_index(index, elements.len())?;
+                elements[index] = value;
+                Ok(())
+            },
+            Object::Map(map) => {
+                map.borrow_mut().insert(MapKey::new(index)?, value);
+                Ok(())
+            },
+            _ => Err(String::from("Only lists and maps can be indexed.")),
+        }
+    }
+
     pub fn is_equal(&self, other: Object) -> bool {
         match (self, other) {
             (Object::Nil, Object::Nil) => true,
             (Object::Unitialized, Object::Unitialized) => true,
             (Object::Nil, _) => false,
             (Object::Unitialized, _) => false,
-            (Object::Number(a1), Object::Number(a2)) => a1.clone() == a2,
-            (Object::Boolean(a1), Object::Boolean(a2)) => a1.clone() == a2,
+            (Object::Number(a1), Object::Number(a2)) => *a1 == a2,
+            (Object::Boolean(a1), Object::Boolean(a2)) => *a1 == a2,
             (Object::String(a1), Object::String(a2)) => *a1 == a2,
+            // The same declaration closing over the same environment.
+            (Object::Function { name: n1, environment: e1, .. }, Object::Function { name: n2, environment: e2, .. }) => {
+                n1.span() == n2.span() && Rc::ptr_eq(e1, &e2)
+            },
+            (Object::Builtin(n1, ..), Object::Builtin(n2, ..)) => *n1 == n2,
+            (Object::Class(a1), Object::Class(a2)) => Rc::ptr_eq(a1, &a2),
+            (Object::Instance(a1), Object::Instance(a2)) => Rc::ptr_eq(a1, &a2),
+            (Object::List(a1), Object::List(a2)) => Rc::ptr_eq(a1, &a2),
+            (Object::Map(a1), Object::Map(a2)) => Rc::ptr_eq(a1, &a2),
+            (Object::Closure(a1), Object::Closure(a2)) => Rc::ptr_eq(a1, &a2),
+            (Object::BoundMethod(a1), Object::BoundMethod(a2)) => Rc::ptr_eq(a1, &a2),
             _ => false
         }
     }
 }
 
+thread_local! {
+    /// Lists and maps being displayed, innermost last.
+    static DISPLAYING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Displays a list or map, or `placeholder` if it contains itself and is
+/// already being displayed further out.
+fn display_once<T>(
+    container: &Rc<RefCell<T>>,
+    placeholder: &str,
+    f: &mut std::fmt::Formatter,
+    display: impl FnOnce(&T, &mut std::fmt::Formatter) -> std::fmt::Result,
+) -> std::fmt::Result {
+    let address = Rc::as_ptr(container) as *const ();
+
+    if DISPLAYING.with(|displaying| displaying.borrow().contains(&address)) {
+        return write!(f, "{}", placeholder);
+    }
+
+    DISPLAYING.with(|displaying| displaying.borrow_mut().push(address));
+    let result = display(&container.borrow(), f);
+    DISPLAYING.with(|displaying| displaying.borrow_mut().pop());
+
+    result
+}
+
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         match self {
@@ -67,54 +222,91 @@ impl Display for Object {
             Object::String(s) => write!(f, "{}", s),
             Object::Nil => write!(f, "nil"),
             Object::Unitialized => write!(f, "unitialized"),
-            Object::Return(object) => write!(f, "return {}", object),
-            Object::Function{params, body, name, ..} => {
-                write!(f, "fn {}({:?}) {:?}", name.lexeme, csv_str(params), body)
-            }
-            Object::Builtin(name, _) => write!(f, "{}", name),
+            Object::Function{name, ..} => write!(f, "<fn {}>", name.lexeme),
+            Object::Builtin(name, ..) => write!(f, "{}", name),
+            Object::Class(class) => write!(f, "{}", class),
+            Object::Instance(instance) => write!(f, "{}", instance.borrow()),
+            Object::List(elements) => display_once(elements, "[...]", f, |elements, f| write!(f, "[{}]", csv_str(elements))),
+            Object::Map(map) => display_once(map, "{...}", f, |map, f| write!(f, "{}", map)),
+            Object::Closure(closure) => write!(f, "{}", closure.function),
+            Object::BoundMethod(bound) => write!(f, "{}", bound.method.function),
         }
     }
 
-    
+
 }
 
-impl ObjectCaller<BObject> for Object{
+impl ObjectCaller<Result<BObject, RuntimeError>> for Object{
     fn is_callable(&self) -> bool{
-        match self {
-            Object::Function{..} => true,
-            Object::Builtin(_, _) => true,
-            _ => false
-        }
+        matches!(self, Object::Function{..} | Object::Builtin(..) | Object::Class(_) | Object::Closure(_) | Object::BoundMethod(_))
     }
-    fn call(&mut self, interpreter: &mut Interpreter, arguments: Box<[BObject]>) -> BObject {
-        match &self {
-            Object::Function{body, name, params, environment} => {
-                let mut env = Environment::new_enclosing(environment.clone());
+    fn call(&mut self, interpreter: &mut Interpreter, paren: &Token, arguments: Box<[BObject]>) -> Result<BObject, RuntimeError> {
+        // A class runs in the frame of its initializer, if it has one.
+        if let Object::Class(_) = self {
+            return self.invoke(interpreter, paren, arguments);
+        }
 
-                let mut i = 0;
-                while i < params.len() {
-                    env.define(params.get(i).unwrap(), arguments.get(i).unwrap().to_owned());
-                    i += 1;
-                }
+        interpreter.push_frame(self.callable_name(), paren)
+            .map_err(|error| interpreter.traceback(error))?;
+        let result = self.invoke(interpreter, paren, arguments)
+            .map_err(|error| interpreter.traceback(error));
+        interpreter.pop_frame();
 
-                interpreter.execute_block(body, Rc::new(RefCell::new(env)));
-                Return::get()
-            },
-            Object::Builtin(_, func) => func(arguments),
-            _ => Box::new(Object::Nil)
-        }
+        result
     }
-    
+
     fn arity(&self) -> usize {
         match self {
             Object::Function{params, ..} => params.len(),
+            Object::Builtin(_, arity, _) => *arity,
+            Object::Class(class) => class.arity(),
+            Object::Closure(closure) => closure.function.arity,
+            Object::BoundMethod(bound) => bound.method.function.arity,
             _ => 0
         }
     }
 }
 
+impl Object {
+    fn invoke(&self, interpreter: &mut Interpreter, paren: &Token, arguments: Box<[BObject]>) -> Result<BObject, RuntimeError> {
+        match self {
+            Object::Function{body, params, environment, is_initializer, ..} => {
+                let mut env = Environment::new_enclosing(environment.clone());
+
+                for (param, argument) in params.iter().zip(arguments.iter()) {
+                    env.define(param, argument.to_owned())?;
+                }
+
+                let value = match interpreter.execute_block(body, Heap::environment(env)) {
+                    Ok(()) | Err(Unwind::Break | Unwind::Continue) => Box::new(Object::Nil),
+                    Err(Unwind::Return(value)) => value,
+                    Err(Unwind::Error(error)) => return Err(error),
+                };
+
+                if *is_initializer {
+                    return environment.borrow().get(Token::with_lexeme(String::from("this"), TokenType::This));
+                }
+
+                Ok(value)
+            },
+            Object::Builtin(_, _, func) => func(arguments).map_err(|message| RuntimeError::new(paren, message)),
+            Object::Class(class) => {
+                let instance = Box::new(Object::Instance(Heap::instance(Instance::new(class.clone()))));
+
+                if let Some(initializer) = class.find_method("init") {
+                    class::bind(&initializer, instance.to_owned())?.call(interpreter, paren, arguments)?;
+                }
+
+                Ok(instance)
+            },
+            _ => Ok(Box::new(Object::Nil))
+        }
+    }
+}
+
 pub trait ObjectCaller<R> {
     fn is_callable(&self) -> bool;
-    fn call(&mut self, interpreter: &mut Interpreter, arguments: Box<[BObject]>) -> R;
+    /// Calls the value, reporting errors raised by builtins at `paren`.
+    fn call(&mut self, interpreter: &mut Interpreter, paren: &Token, arguments: Box<[BObject]>) -> R;
     fn arity(&self) -> usize;
-}
\ No newline at end of file
+}
@@ -1,46 +1,162 @@
-use crate::token::{Token, TokenType};
+use std::fmt::Display;
 
-static mut HAS_ERROR: bool = false;
+use crate::token::{Span, Token, TokenType};
 
+/// Prints errors on stderr, quoting the lines of the program they point at.
 pub struct ErrorHandler{}
 
 impl ErrorHandler{
-    pub fn runtime_error(operator: &Token, message: String){
-        let message = format!("[line {}] Runtime Error: {}", operator.line, message);
+    /// Prints the errors that stopped `source` from compiling.
+    pub fn report(diagnostics: &[Diagnostic], source: &str) {
+        for diagnostic in diagnostics.iter() {
+            eprintln!("{}", diagnostic);
+            Self::snippet(diagnostic.span, source);
+        }
+    }
+
+    pub fn runtime_error(error: &RuntimeError, source: &str){
+        eprintln!("{}", error);
+
+        // A function defined by an earlier REPL input fails with a token
+        // from that input, which `source` does not contain.
+        let span = error.token.span();
+        let from_source = error.token.lexeme.is_empty()
+            || source.get(span.offset..span.offset + span.length) == Some(error.token.lexeme.as_str());
+        if from_source {
+            Self::snippet(span, source);
+        }
+
+        // Deep recursion repeats the same frame; print a long run of it once.
+        let mut index = 0;
+        while index < error.trace.len() {
+            let frame = &error.trace[index];
+            let repeats = error.trace[index..].iter().take_while(|other| *other == frame).count();
 
-        eprintln!("{}", message);
+            if repeats < 3 {
+                eprintln!("    at {}", frame);
+                index += 1;
+                continue;
+            }
 
-        std::process::exit(70)
+            eprintln!("    at {}", frame);
+            eprintln!("    ... previous frame repeated {} more times", repeats - 1);
+            index += repeats;
+        }
     }
     
-    pub fn error(line: usize, message: String){
-        Self::report(line, String::new(), message)
-    }
-
-    pub fn error_token(token: Token, message: String) {
+    fn location(token: &Token) -> String {
         if token.token_type == TokenType::Eof{
-            Self::report(token.line, String::from(" at end"), message)
+            String::from(" at end")
         } else{
-            let wher = format!(" at '{}'", token.lexeme);
-            Self::report(token.line, wher, message)
+            format!(" at '{}'", token.lexeme)
         }
     }
 
-    fn report(line: usize, wher: String, message: String){
-        eprintln!("[line {0}] Error{1}: {2}", line, wher, message);
-        unsafe { HAS_ERROR = true };
+    /// Prints the source line containing `span` with the spanned text
+    /// underlined, in the style of rustc diagnostics.
+    fn snippet(span: Span, source: &str) {
+        if span.line == 0 {
+            return;
+        }
+
+        let Some(text) = source.lines().nth(span.line - 1) else {
+            return;
+        };
+
+        let indent: String = text.chars()
+            .take(span.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let spanned = source.get(span.offset..span.offset + span.length)
+            .map(|spanned| spanned.chars().take_while(|c| *c != '\n').count())
+            .unwrap_or(1);
+        let available = text.chars().count().saturating_sub(span.column.saturating_sub(1));
+        let width = spanned.min(available).max(1);
+
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        eprintln!("{}--> {}:{}", gutter, span.line, span.column);
+        eprintln!("{} |", gutter);
+        eprintln!("{} | {}", number, text);
+        eprintln!("{} | {}{}", gutter, indent, "^".repeat(width));
+    }
+}
+
+/// An error that stops a program from compiling, found while scanning,
+/// parsing, resolving or compiling it to bytecode.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub span: Span,
+    /// Where the error is, such as ` at 'x'` or ` at end`, or empty.
+    pub location: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn at(span: Span, message: String) -> Self {
+        Self { span, location: String::new(), message }
+    }
+
+    pub fn at_token(token: &Token, message: String) -> Self {
+        Self { span: token.span(), location: ErrorHandler::location(token), message }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Self::at_token(&error.token, error.message.to_owned())
     }
+}
 
-    pub fn had_error() -> bool {
-        unsafe { HAS_ERROR }
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[line {}] Error{}: {}", self.span.line, self.location, self.message)
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct ParserError{}
+pub struct RuntimeError{
+    pub token: Box<Token>,
+    pub message: String,
+    /// Calls the error unwound through, innermost first.
+    pub trace: Vec<String>,
+}
+
+impl RuntimeError {
+    pub fn new(token: &Token, message: String) -> Self {
+        Self { token: Box::new(token.to_owned()), message, trace: Vec::new() }
+    }
+
+    /// Error at a source range rather than a token, as in compiled code.
+    /// Only the position of the token it keeps is used.
+    pub fn at(span: Span, message: String) -> Self {
+        Self { token: Box::new(Token { length: span.length, ..Token::eof(span) }), message, trace: Vec::new() }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[line {}] Runtime Error: {}", self.token.line, self.message)
+    }
+}
+
+/// A syntax error found by the parser.
+#[derive(Debug, Clone)]
+pub struct ParseError{
+    pub token: Token,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(token: &Token, message: String) -> Self {
+        Self { token: token.to_owned(), message }
+    }
+}
 
-impl ParserError {
-    pub fn new() -> Self {
-        Self {  }
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[line {}] Error{}: {}", self.token.line, ErrorHandler::location(&self.token), self.message)
     }
 }
@@ -1,12 +1,13 @@
-use std::fmt::Display;
+use std::{fmt::Display, sync::atomic::{AtomicUsize, Ordering}};
 
-use crate::{environment::{BObject, Object}, error::ParserError, token::Token};
+use crate::{environment::BObject, error::ParseError, statement::Stmt, token::{Span, Token}};
 
-pub type MyResult<T> = std::result::Result<Box<T>, ParserError>;
+pub type MyResult<T> = std::result::Result<Box<T>, ParseError>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Expr {
     Assign{
+        id: usize,
         name: Token,
         value: Box<Expr>
     },
@@ -25,10 +26,43 @@ pub enum Expr {
         name: Token
     },
     Grouping{
-        expression: Box<Expr>
+        expression: Box<Expr>,
+        span: Span
+    },
+    /// `object[index]`, where `bracket` is the closing bracket.
+    Index{
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>
+    },
+    /// String literal with embedded expressions. Text segments are string
+    /// literals and alternate with the expressions, starting and ending with text.
+    Interpolation{
+        parts: Box<[Box<Expr>]>
     },
     Literal{
-        value: BObject
+        value: BObject,
+        /// Number literal as written, such as `0x1F` or `1_000`, which the
+        /// formatter keeps rather than printing the value.
+        lexeme: Option<String>,
+        span: Span
+    },
+    /// Anonymous function. `keyword` is `fun`, or `=>` for an arrow function
+    /// whose body is a single return of its expression.
+    Lambda{
+        keyword: Token,
+        params: Box<[Token]>,
+        body: Box<[Box<Stmt>]>,
+        span: Span
+    },
+    List{
+        elements: Box<[Box<Expr>]>,
+        span: Span
+    },
+    Map{
+        brace: Token,
+        entries: Box<[(Box<Expr>, Box<Expr>)]>,
+        span: Span
     },
     Logical{
         left: Box<Expr>,
@@ -36,15 +70,24 @@ pub enum Expr {
         right: Box<Expr>,
     },
     Set{
-        left: Box<Expr>,
+        object: Box<Expr>,
         name: Token,
-        right: Box<Expr>,
+        value: Box<Expr>,
+    },
+    /// `object[index] = value`, where `bracket` is the closing bracket.
+    SetIndex{
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
     },
     Super{
+        id: usize,
         keyword: Token,
         method: Token
     },
     This{
+        id: usize,
         keyword: Token,
     },
     Unary {
@@ -52,17 +95,49 @@ pub enum Expr {
         right: Box<Expr>,
     },
     Variable{
+        id: usize,
         name: Token
     },
 }
 
 impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+        write!(f, "{:?}", self)
     }
 }
 
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
 impl Expr {
+    /// Unique id used by the resolver to identify variable-referencing nodes.
+    pub fn next_id() -> usize {
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Source range covered by the expression.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign { name, value, .. } => name.span().to(value.span()),
+            Expr::Binary { left, right, .. } => left.span().to(right.span()),
+            Expr::Call { callee, paren, .. } => callee.span().to(paren.span()),
+            Expr::Get { object, name } => object.span().to(name.span()),
+            Expr::Grouping { span, .. } => *span,
+            Expr::Index { object, bracket, .. } => object.span().to(bracket.span()),
+            Expr::Interpolation { parts } => parts[0].span().to(parts[parts.len() - 1].span()),
+            Expr::Literal { span, .. } => *span,
+            Expr::Lambda { span, .. } => *span,
+            Expr::List { span, .. } => *span,
+            Expr::Map { span, .. } => *span,
+            Expr::Logical { left, right, .. } => left.span().to(right.span()),
+            Expr::Set { object, value, .. } => object.span().to(value.span()),
+            Expr::SetIndex { object, value, .. } => object.span().to(value.span()),
+            Expr::Super { keyword, method, .. } => keyword.span().to(method.span()),
+            Expr::This { keyword, .. } => keyword.span(),
+            Expr::Unary { operator, right } => operator.span().to(right.span()),
+            Expr::Variable { name, .. } => name.span(),
+        }
+    }
+
     pub fn wrap(self) -> MyResult<Expr>{
         Ok(Box::new(self))
     }
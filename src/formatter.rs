@@ -0,0 +1,426 @@
+use crate::{environment::Object, expression::{Expr, ExprVisitor}, statement::{LoopSyntax, Stmt, StmtVisitor}, token::{Token, TokenType}};
+
+const INDENT: &str = "    ";
+
+/// Regenerates canonical Lox source from a parsed program.
+#[derive(Default)]
+pub struct Formatter{
+    indent: usize,
+    lines: Vec<String>,
+}
+
+impl Formatter {
+    pub fn new() -> Self {
+        Self { indent: 0, lines: Vec::new() }
+    }
+
+    pub fn format(&mut self, stmts: &[Box<Stmt>]) -> String{
+        self.lines.clear();
+        self.declarations(stmts, false);
+
+        let mut source = self.lines.join("\n");
+        source.push('\n');
+        source
+    }
+
+    pub fn format_expr(&mut self, expr: &Expr) -> String{
+        expr.accept(self)
+    }
+
+    fn format_stmt(&mut self, stmt: &Stmt) {
+        stmt.accept(self)
+    }
+
+    /// First non-comment statement at or after `index`, so that comments
+    /// stay attached to the declaration they describe.
+    fn declaration_at(stmts: &[Box<Stmt>], index: usize) -> Option<&Stmt> {
+        stmts[index..].iter()
+            .map(|stmt| stmt.as_ref())
+            .find(|stmt| !matches!(stmt, Stmt::Comment { .. }))
+    }
+
+    /// Emits top-level statements or class methods, separating functions and
+    /// classes from their neighbours with a blank line.
+    fn declarations(&mut self, stmts: &[Box<Stmt>], methods: bool) {
+        let mut previous: Option<&Stmt> = None;
+
+        for (index, stmt) in stmts.iter().enumerate() {
+            if let Some(previous) = previous {
+                let next = Self::declaration_at(stmts, index);
+                let after_comment = matches!(stmts[index - 1].as_ref(), Stmt::Comment { trailing: false, .. });
+
+                if !Self::is_trailing(stmt) && !after_comment && (methods || Self::is_declaration(previous) || next.is_some_and(Self::is_declaration)) {
+                    self.lines.push(String::new());
+                }
+            }
+
+            match stmt.as_ref() {
+                Stmt::Function { name, params, body, doc, .. } if methods => {
+                    self.doc(doc);
+                    self.function("", name, params, body)
+                },
+                _ => self.format_stmt(stmt),
+            }
+
+            if !matches!(stmt.as_ref(), Stmt::Comment { .. }) {
+                previous = Some(stmt);
+            }
+        }
+    }
+
+    /// Writes a string value back as the body of a string literal.
+    fn escape(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        let mut chars = value.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '$' if chars.peek() == Some(&'{') => escaped.push_str("\\$"),
+                '\n' => escaped.push_str("\\n"),
+                '\t' => escaped.push_str("\\t"),
+                '\r' => escaped.push_str("\\r"),
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\0' => escaped.push_str("\\0"),
+                c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                c => escaped.push(c),
+            }
+        }
+
+        escaped
+    }
+
+    fn is_declaration(stmt: &Stmt) -> bool {
+        matches!(stmt, Stmt::Function { .. } | Stmt::Class { .. })
+    }
+
+    fn is_trailing(stmt: &Stmt) -> bool {
+        matches!(stmt, Stmt::Comment { trailing: true, .. })
+    }
+
+    fn line(&mut self, text: String) {
+        self.lines.push(format!("{}{}", INDENT.repeat(self.indent), text));
+    }
+
+    /// Appends `text` to the last emitted line, e.g. an opening brace or `else`.
+    fn append(&mut self, text: &str) {
+        match self.lines.last_mut() {
+            Some(last) => last.push_str(text),
+            None => self.lines.push(text.to_owned()),
+        }
+    }
+
+    fn block(&mut self, statements: &[Box<Stmt>]) {
+        if statements.is_empty() {
+            self.append(" {}");
+            return;
+        }
+
+        self.append(" {");
+        self.indent += 1;
+
+        for stmt in statements.iter() {
+            self.format_stmt(stmt);
+        }
+
+        self.indent -= 1;
+        self.line(String::from("}"));
+    }
+
+    /// Emits the body of `if`, `while` and `for`, keeping blocks on the header
+    /// line and moving single statements onto their own indented line.
+    fn body(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { statements, .. } => self.block(statements),
+            _ => {
+                self.indent += 1;
+                self.format_stmt(stmt);
+                self.indent -= 1;
+            }
+        }
+    }
+
+    fn branches(&mut self, then_branch: &Stmt, else_branch: &Option<Box<Stmt>>) {
+        self.body(then_branch);
+
+        let Some(else_branch) = else_branch else {
+            return;
+        };
+
+        if matches!(then_branch, Stmt::Block { .. }) {
+            self.append(" else");
+        } else {
+            self.line(String::from("else"));
+        }
+
+        match else_branch.as_ref() {
+            // Chain `else if` on the same line instead of nesting it.
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                let condition = self.format_expr(condition);
+                self.append(&format!(" if ({})", condition));
+                self.branches(then_branch, else_branch);
+            },
+            _ => self.body(else_branch),
+        }
+    }
+
+    fn doc(&mut self, doc: &Option<String>) {
+        let Some(doc) = doc else {
+            return;
+        };
+
+        for line in doc.lines() {
+            if line.is_empty() {
+                self.line(String::from("///"));
+            } else {
+                self.line(format!("/// {}", line));
+            }
+        }
+    }
+
+    fn params(params: &[Token]) -> String {
+        params.iter()
+            .map(|param| param.lexeme.to_owned())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn function(&mut self, prefix: &str, name: &Token, params: &[Token], body: &[Box<Stmt>]) {
+        self.line(format!("{}{}({})", prefix, name.lexeme, Self::params(params)));
+        self.block(body);
+    }
+
+    /// Expression returned by an arrow function.
+    fn arrow_value<'a>(keyword: &Token, body: &'a [Box<Stmt>]) -> Option<&'a Expr> {
+        match body {
+            [stmt] if keyword.token_type == TokenType::Arrow => match stmt.as_ref() {
+                Stmt::Return { value, .. } => value.as_deref(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Source of a block inside an expression, from the opening brace on,
+    /// indented to continue the current line.
+    fn inline_block(&mut self, statements: &[Box<Stmt>]) -> String {
+        let mut nested = Formatter { indent: self.indent, lines: vec![String::new()] };
+        nested.block(statements);
+        nested.lines.join("\n")
+    }
+
+    fn var(&mut self, name: &Token, initializer: &Expr) -> String {
+        match initializer {
+            Expr::Literal { value, .. } if matches!(value.as_ref(), Object::Unitialized) => {
+                format!("var {}", name.lexeme)
+            },
+            _ => format!("var {} = {}", name.lexeme, self.format_expr(initializer)),
+        }
+    }
+
+    /// Source of a `for` loop initializer clause, without the trailing semicolon.
+    fn for_initializer(&mut self, stmt: &Stmt) -> Option<String> {
+        match stmt {
+            Stmt::Var { name, initializer, .. } => Some(self.var(name, initializer)),
+            Stmt::Expression { expression, .. } => Some(self.format_expr(expression)),
+            _ => None,
+        }
+    }
+
+    /// Prints a desugared `for` loop with the clauses it was written with.
+    fn for_loop(&mut self, initializer: Option<String>, condition: Option<&Expr>, increment: Option<&Expr>, body: &Stmt) {
+        let condition = condition.map(|condition| format!(" {}", self.format_expr(condition))).unwrap_or_default();
+        let increment = increment.map(|increment| format!(" {}", self.format_expr(increment))).unwrap_or_default();
+
+        self.line(format!("for ({};{};{})", initializer.unwrap_or_default(), condition, increment));
+        self.body(body);
+    }
+}
+
+impl StmtVisitor<()> for Formatter {
+    fn visit(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { statements, .. } => {
+                // The parser wraps a `for` loop with an initializer in a block.
+                if let [initializer, body] = statements.as_ref() {
+                    if let Stmt::While { condition, body, increment, syntax: LoopSyntax::For { initializer: true, condition: has_condition }, .. } = body.as_ref() {
+                        if let Some(initializer) = self.for_initializer(initializer) {
+                            let condition = has_condition.then_some(condition.as_ref());
+                            self.for_loop(Some(initializer), condition, increment.as_deref(), body);
+                            return;
+                        }
+                    }
+                }
+
+                self.line(String::from("{"));
+                self.indent += 1;
+
+                for stmt in statements.iter() {
+                    self.format_stmt(stmt);
+                }
+
+                self.indent -= 1;
+                self.line(String::from("}"));
+            },
+            Stmt::Class { name, superclass, methods, doc, .. } => {
+                self.doc(doc);
+
+                let header = match superclass {
+                    Some(superclass) => format!("class {} < {}", name.lexeme, self.format_expr(superclass)),
+                    None => format!("class {}", name.lexeme),
+                };
+                self.line(header);
+
+                if methods.is_empty() {
+                    self.append(" {}");
+                    return;
+                }
+
+                self.append(" {");
+                self.indent += 1;
+
+                self.declarations(methods, true);
+
+                self.indent -= 1;
+                self.line(String::from("}"));
+            },
+            Stmt::Break { .. } => self.line(String::from("break;")),
+            Stmt::Comment { text, trailing, .. } => {
+                if *trailing && !self.lines.is_empty() {
+                    self.append(&format!(" {}", text));
+                } else {
+                    self.line(text.to_owned());
+                }
+            },
+            Stmt::Continue { .. } => self.line(String::from("continue;")),
+            Stmt::Expression { expression, .. } => {
+                let expression = self.format_expr(expression);
+                self.line(format!("{};", expression));
+            },
+            Stmt::Function { name, params, body, doc, .. } => {
+                self.doc(doc);
+                self.function("fun ", name, params, body)
+            },
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                let condition = self.format_expr(condition);
+                self.line(format!("if ({})", condition));
+                self.branches(then_branch, else_branch);
+            },
+            Stmt::Print { expression, .. } => {
+                let expression = self.format_expr(expression);
+                self.line(format!("print {};", expression));
+            },
+            Stmt::Return { value, .. } => {
+                match value {
+                    Some(value) => {
+                        let value = self.format_expr(value);
+                        self.line(format!("return {};", value));
+                    },
+                    None => self.line(String::from("return;")),
+                }
+            },
+            Stmt::Var { name, initializer, .. } => {
+                let var = self.var(name, initializer);
+                self.line(format!("{};", var));
+            },
+            Stmt::While { condition, body, increment, syntax, .. } => {
+                match syntax {
+                    LoopSyntax::For { condition: has_condition, .. } => {
+                        let condition = has_condition.then_some(condition.as_ref());
+                        self.for_loop(None, condition, increment.as_deref(), body);
+                    },
+                    LoopSyntax::While => {
+                        let condition = self.format_expr(condition);
+                        self.line(format!("while ({})", condition));
+                        self.body(body);
+                    }
+                }
+            },
+        }
+    }
+}
+
+impl ExprVisitor<String> for Formatter {
+    fn visit(&mut self, expr: &Expr) -> String {
+        match expr {
+            Expr::Assign { name, value, .. } => format!("{} = {}", name.lexeme, self.format_expr(value)),
+            Expr::Binary { left, operator, right } => {
+                format!("{} {} {}", self.format_expr(left), operator.lexeme, self.format_expr(right))
+            },
+            Expr::Call { callee, arguments, .. } => {
+                let callee = self.format_expr(callee);
+                let arguments = arguments.iter()
+                    .map(|argument| self.format_expr(argument))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                format!("{}({})", callee, arguments)
+            },
+            Expr::Get { object, name } => format!("{}.{}", self.format_expr(object), name.lexeme),
+            Expr::Grouping { expression, .. } => format!("({})", self.format_expr(expression)),
+            Expr::Index { object, index, .. } => format!("{}[{}]", self.format_expr(object), self.format_expr(index)),
+            Expr::Interpolation { parts } => {
+                let mut literal = String::from("\"");
+
+                for part in parts.iter() {
+                    match part.as_ref() {
+                        Expr::Literal { value, .. } if matches!(value.as_ref(), Object::String(_)) => {
+                            literal.push_str(&Self::escape(&value.to_string()));
+                        },
+                        expr => literal.push_str(&format!("${{{}}}", self.format_expr(expr))),
+                    }
+                }
+
+                literal.push('"');
+                literal
+            },
+            Expr::Lambda { keyword, params, body, .. } => {
+                let params = Self::params(params);
+
+                match Self::arrow_value(keyword, body) {
+                    Some(value) => format!("({}) => {}", params, self.format_expr(value)),
+                    None => format!("fun ({}){}", params, self.inline_block(body)),
+                }
+            },
+            Expr::List { elements, .. } => {
+                let elements = elements.iter()
+                    .map(|element| self.format_expr(element))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                format!("[{}]", elements)
+            },
+            Expr::Literal { value, lexeme, .. } => {
+                if let Some(lexeme) = lexeme {
+                    return lexeme.to_owned();
+                }
+
+                match value.as_ref() {
+                    Object::String(s) => format!("\"{}\"", Self::escape(s)),
+                    other => other.to_string(),
+                }
+            },
+            Expr::Map { entries, .. } => {
+                let entries = entries.iter()
+                    .map(|(key, value)| format!("{}: {}", self.format_expr(key), self.format_expr(value)))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                format!("{{{}}}", entries)
+            },
+            Expr::Logical { left, operator, right } => {
+                format!("{} {} {}", self.format_expr(left), operator.lexeme, self.format_expr(right))
+            },
+            Expr::Set { object, name, value } => {
+                format!("{}.{} = {}", self.format_expr(object), name.lexeme, self.format_expr(value))
+            },
+            Expr::SetIndex { object, index, value, .. } => {
+                format!("{}[{}] = {}", self.format_expr(object), self.format_expr(index), self.format_expr(value))
+            },
+            Expr::Super { method, .. } => format!("super.{}", method.lexeme),
+            Expr::This { .. } => String::from("this"),
+            Expr::Unary { operator, right } => format!("{}{}", operator.lexeme, self.format_expr(right)),
+            Expr::Variable { name, .. } => name.lexeme.to_owned(),
+        }
+    }
+}
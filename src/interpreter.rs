@@ -1,126 +1,306 @@
-use std::{cell::RefCell, io::Error, panic::UnwindSafe, rc::Rc};
+use std::{collections::HashMap, io::{self, Write}, rc::Rc};
 
-use crate::{environment::{environment::{Environment, MutEnv}, BObject, Object, ObjectCaller}, error::ErrorHandler, expression::{Expr, ExprVisitor}, returner::Return, statement::{Stmt, StmtVisitor}, token::{Token, TokenType}};
+use crate::{environment::{class::{self, Class}, environment::{Environment, MutEnv}, heap::Heap, map::{Map, MapKey}, BObject, Object, ObjectCaller}, error::RuntimeError, expression::{Expr, ExprVisitor}, returner::{ExecResult, Unwind}, statement::{Stmt, StmtVisitor}, token::{Token, TokenType}};
+
+/// Calls that may be nested before a call fails with a stack overflow.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// Native stack reserved per nested call, enough for a call in the middle
+/// of a deeply nested expression in an unoptimized build.
+const CALL_STACK_SIZE: usize = 64 * 1024;
+
+/// Largest native stack [`stack_size`] asks for.
+const MAX_STACK_SIZE: usize = 1024 * 1024 * 1024;
+
+/// Native stack a thread needs to run the interpreter with `max_depth`
+/// nested calls, or `None` if that would be more than 1 GiB.
+pub fn stack_size(max_depth: usize) -> Option<usize> {
+    max_depth.checked_add(64)?
+        .checked_mul(CALL_STACK_SIZE)
+        .filter(|size| *size <= MAX_STACK_SIZE)
+}
+
+/// Function call in progress.
+struct CallFrame {
+    name: String,
+    /// Line of the call site in the caller.
+    line: usize,
+}
 
 pub struct Interpreter{
     environment: MutEnv,
-    pub globals: MutEnv
+    pub globals: MutEnv,
+    /// Builtins, looked up after the globals.
+    pub prelude: MutEnv,
+    locals: HashMap<usize, usize>,
+    frames: Vec<CallFrame>,
+    max_depth: usize,
+    output: Box<dyn Write>
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        let environment = Rc::new(RefCell::new(Environment::new()));
+        let prelude = Heap::environment(Environment::prelude());
+        let environment = Heap::environment(Environment::new_enclosing(prelude.clone()));
 
         Self{
             environment: environment.to_owned(),
-            globals: environment.clone()
+            globals: environment.clone(),
+            prelude,
+            locals: HashMap::new(),
+            frames: Vec::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            output: Box::new(io::stdout())
+        }
+    }
+
+    /// Redirects the output of `print` statements.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    /// Limits how deeply calls may nest. Each call also recurses natively,
+    /// so the thread running the interpreter needs a stack of
+    /// [`stack_size`] bytes to reach the limit instead of aborting.
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Records how many scopes away the variable referenced by expression `id` lives.
+    pub fn resolve(&mut self, id: usize, depth: usize) {
+        self.locals.insert(id, depth);
+    }
+
+    /// Enters a call made at `paren`, failing if calls are nested too deeply.
+    pub fn push_frame(&mut self, name: String, paren: &Token) -> Result<(), RuntimeError> {
+        if self.frames.len() >= self.max_depth {
+            return Interpreter::runtime_error(paren, String::from("Stack overflow."));
         }
+
+        self.frames.push(CallFrame { name, line: paren.line });
+        Ok(())
+    }
+
+    pub fn pop_frame(&mut self) {
+        self.frames.pop();
     }
 
-    pub fn evaluate_expr(&mut self, expr: &Box<Expr>) -> BObject {
+    /// Records the active calls as the error's trace, each with the line it
+    /// had reached and ending with the script, unless a deeper call already did.
+    pub fn traceback(&self, mut error: RuntimeError) -> RuntimeError {
+        if !error.trace.is_empty() {
+            return error;
+        }
+
+        let mut line = error.token.line;
+
+        for frame in self.frames.iter().rev() {
+            error.trace.push(format!("{} (line {})", frame.name, line));
+            line = frame.line;
+        }
+
+        error.trace.push(format!("<script> (line {})", line));
+        error
+    }
+
+    fn look_up_variable(&self, id: usize, name: &Token) -> Result<BObject, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(distance) => Environment::get_at(&self.environment, *distance, name.to_owned()),
+            None => self.globals.borrow().get(name.to_owned()),
+        }
+    }
+
+    /// Executes top-level statements, stopping at the first runtime error.
+    pub fn interpret(&mut self, statements: &[Box<Stmt>]) -> Result<(), RuntimeError> {
+        for stmt in statements.iter() {
+            match self.evaluate_stmt(stmt) {
+                Ok(()) | Err(Unwind::Return(_) | Unwind::Break | Unwind::Continue) => {},
+                Err(Unwind::Error(error)) => return Err(self.traceback(error)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates an expression typed at top level, such as at the prompt.
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<BObject, RuntimeError> {
+        self.evaluate_expr(expr).map_err(|error| self.traceback(error))
+    }
+
+    pub fn evaluate_expr(&mut self, expr: &Expr) -> Result<BObject, RuntimeError> {
         expr.accept(self)
     }
 
-    pub fn evaluate_stmt(&mut self, stmt: &Box<Stmt>) -> (){
+    pub fn evaluate_stmt(&mut self, stmt: &Stmt) -> ExecResult {
         stmt.accept(self)
     }
 
-    fn runtime_error(operator: &Token, message: String) -> BObject{
-        ErrorHandler::runtime_error(operator, String::from(message));
-        Box::new(Object::Nil)
+    fn runtime_error<T>(operator: &Token, message: String) -> Result<T, RuntimeError> {
+        Err(RuntimeError::new(operator, message))
     }
 
-    pub fn execute_block(&mut self, statements: &Box<[Box<Stmt>]>, environment: MutEnv) {
+    pub fn execute_block(&mut self, statements: &[Box<Stmt>], environment: MutEnv) -> ExecResult {
         let previous = self.environment.to_owned();
 
         self.environment = environment;
 
-        for stmt in statements.iter() {
-            self.evaluate_stmt(stmt)
+        let result = statements.iter()
+            .try_for_each(|stmt| self.evaluate_stmt(stmt));
+
+        self.environment = previous;
+
+        result
+    }
+
+    fn class_declaration(&mut self, name: &Token, superclass: &Option<Box<Expr>>, methods: &[Box<Stmt>]) -> Result<(), RuntimeError> {
+        let superclass = match superclass {
+            Some(expr) => {
+                match *self.evaluate_expr(expr)? {
+                    Object::Class(class) => Some(class),
+                    _ => {
+                        let token = match expr.as_ref() {
+                            Expr::Variable { name, .. } => name,
+                            _ => name,
+                        };
+                        return Interpreter::runtime_error(token, String::from("Superclass must be a class."));
+                    }
+                }
+            },
+            None => None,
+        };
+
+        self.environment.borrow_mut().define(name, Box::new(Object::Nil))?;
+
+        let previous = self.environment.to_owned();
+
+        if let Some(superclass) = &superclass {
+            let mut env = Environment::new_enclosing(self.environment.to_owned());
+            env.define(&Token::with_lexeme(String::from("super"), TokenType::Super), Box::new(Object::Class(superclass.clone())))?;
+            self.environment = Heap::environment(env);
+        }
+
+        let mut class_methods = HashMap::new();
+
+        for method in methods.iter() {
+            if let Stmt::Function { name, params, body, .. } = method.as_ref() {
+                let function = Object::Function {
+                    name: Box::new(name.to_owned()),
+                    params: params.to_owned(),
+                    body: body.to_owned(),
+                    environment: self.environment.clone(),
+                    is_initializer: name.lexeme == "init"
+                };
+                class_methods.insert(name.lexeme.to_owned(), Box::new(function));
+            }
         }
 
         self.environment = previous;
+
+        let class = Class::new(name.lexeme.to_owned(), superclass, class_methods);
+        self.environment.borrow_mut().assign(name, Box::new(Object::Class(Rc::new(class))))
     }
 }
 
-impl StmtVisitor<()> for Interpreter {
-    fn visit(&mut self, stmt: &Stmt) -> () {
+impl StmtVisitor<ExecResult> for Interpreter {
+    fn visit(&mut self, stmt: &Stmt) -> ExecResult {
         match stmt {
-            Stmt::Print { expression } => {
-                let value = self.evaluate_expr(expression);
-                println!("{}", value)
+            Stmt::Print { expression, .. } => {
+                let value = self.evaluate_expr(expression)?;
+                writeln!(self.output, "{}", value).expect("Failed to write output.");
             },
-            Stmt::Expression { expression } => {
-                self.evaluate_expr(expression);
+            Stmt::Expression { expression, .. } => {
+                self.evaluate_expr(expression)?;
             },
-            Stmt::Block { statements } => {
+            Stmt::Block { statements, .. } => {
                 let new_enw = Environment::new_enclosing(self.environment.to_owned());
 
-                self.execute_block(statements, 
-                    Rc::new(RefCell::new(new_enw)))
+                return self.execute_block(statements,
+                    Heap::environment(new_enw))
             },
-            Stmt::Return { keyword, value } => {
+            Stmt::Return { value, .. } => {
                 let mut return_value = Box::new(Object::Nil);
 
-                match value {
-                    Some(value) => {
-                        return_value = self.evaluate_expr(value);
-                    },
-                    None => {},
+                if let Some(value) = value {
+                    return_value = self.evaluate_expr(value)?;
                 }
-                
-                Return::add(return_value)
+
+                return Err(Unwind::Return(return_value))
             },
-            Stmt::Var { name, initializer } => {
-                let value = self.evaluate_expr(initializer);
-                self.environment.borrow_mut().define(name, value)
+            Stmt::Var { name, initializer, .. } => {
+                let value = self.evaluate_expr(initializer)?;
+                self.environment.borrow_mut().define(name, value)?;
             },
-            Stmt::While { condition, body } => {
-                while self.evaluate_expr(condition).is_thuthy() {
-                    self.evaluate_stmt(body);
+            Stmt::While { condition, body, increment, .. } => {
+                while self.evaluate_expr(condition)?.is_thuthy() {
+                    match self.evaluate_stmt(body) {
+                        Ok(()) | Err(Unwind::Continue) => {},
+                        Err(Unwind::Break) => break,
+                        Err(unwind) => return Err(unwind),
+                    }
+
+                    // `continue` in a `for` loop still runs the increment clause.
+                    if let Some(increment) = increment {
+                        self.evaluate_expr(increment)?;
+                    }
                 }
             },
-            Stmt::If { condition, then_branch, else_branch } => {
-                let condition_result = self.evaluate_expr(condition);
+            Stmt::Comment { .. } => {},
+            Stmt::Break { .. } => return Err(Unwind::Break),
+            Stmt::Continue { .. } => return Err(Unwind::Continue),
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                let condition_result = self.evaluate_expr(condition)?;
 
                 if condition_result.is_thuthy(){
-                    self.evaluate_stmt(then_branch)
-                } else {
-                    match else_branch {
-                        Some(branch) => self.evaluate_stmt(branch),
-                        None => (),
-                    }
+                    return self.evaluate_stmt(then_branch)
+                } else if let Some(branch) = else_branch {
+                    return self.evaluate_stmt(branch)
                 }
             },
-            Stmt::Function { name, params, body } => {
+            Stmt::Function { name, params, body, .. } => {
                 let function = Object::Function{
                     body: body.to_owned(),
                     name: Box::new(name.to_owned()),
                     params: params.to_owned(),
-                    environment: self.environment.clone() 
+                    environment: self.environment.clone(),
+                    is_initializer: false
                 };
-                self.environment.borrow_mut().define(name, Box::new(function))
+                self.environment.borrow_mut().define(name, Box::new(function))?;
+            },
+            Stmt::Class { name, superclass, methods, .. } => {
+                self.class_declaration(name, superclass, methods)?;
             },
-            _ => panic!("Statement not defined!")
         }
+
+        Ok(())
     }
 }
 
-impl ExprVisitor<BObject> for Interpreter {
-    fn visit(&mut self, expr: &Expr) -> BObject {
+impl ExprVisitor<Result<BObject, RuntimeError>> for Interpreter {
+    fn visit(&mut self, expr: &Expr) -> Result<BObject, RuntimeError> {
         match expr {
-            Expr::Assign { name, value } => {
-                let value = self.evaluate_expr(value);
-                self.environment.borrow_mut().assign(name, value.to_owned());
-                return value;
+            Expr::Assign { id, name, value } => {
+                let value = self.evaluate_expr(value)?;
+
+                match self.locals.get(id) {
+                    Some(distance) => Environment::assign_at(&self.environment, *distance, name, value.to_owned())?,
+                    None => self.globals.borrow_mut().assign(name, value.to_owned())?,
+                }
+
+                Ok(value)
             },
             Expr::Call { callee, paren, arguments } => {
-                let mut callee = self.evaluate_expr(callee);
+                let mut callee = self.evaluate_expr(callee)?;
 
                 let mut args = vec![];
 
                 for arg in arguments.iter() {
-                    args.push(self.evaluate_expr(arg));
+                    args.push(self.evaluate_expr(arg)?);
                 }
 
                 if !callee.is_callable(){
@@ -131,84 +311,132 @@ impl ExprVisitor<BObject> for Interpreter {
                     return Interpreter::runtime_error(paren, format!("Expected {} arguments, but got {}.", callee.arity(), args.len()));
                 }
 
-                callee.call(self, args.into_boxed_slice())
+                callee.call(self, paren, args.into_boxed_slice())
+            },
+            Expr::Get { object, name } => {
+                match *self.evaluate_expr(object)? {
+                    Object::Instance(instance) => class::get_property(&instance, name),
+                    _ => Interpreter::runtime_error(name, String::from("Only instances have properties.")),
+                }
+            },
+            Expr::Index { object, bracket, index } => {
+                let object = self.evaluate_expr(object)?;
+                let index = self.evaluate_expr(index)?;
+
+                object.get_index(&index).map_err(|message| RuntimeError::new(bracket, message))
+            },
+            Expr::SetIndex { object, bracket, index, value } => {
+                let object = self.evaluate_expr(object)?;
+                let index = self.evaluate_expr(index)?;
+                let value = self.evaluate_expr(value)?;
+
+                object.set_index(&index, value.to_owned()).map_err(|message| RuntimeError::new(bracket, message))?;
+                Ok(value)
+            },
+            Expr::Lambda { keyword, params, body, .. } => {
+                let name = Token { lexeme: String::from("<lambda>"), ..keyword.to_owned() };
+
+                Ok(Box::new(Object::Function{
+                    body: body.to_owned(),
+                    name: Box::new(name),
+                    params: params.to_owned(),
+                    environment: self.environment.clone(),
+                    is_initializer: false
+                }))
+            },
+            Expr::List { elements, .. } => {
+                let mut values = Vec::with_capacity(elements.len());
+
+                for element in elements.iter() {
+                    values.push(self.evaluate_expr(element)?);
+                }
+
+                Ok(Box::new(Object::List(Heap::list(values))))
+            },
+            Expr::Map { brace, entries, .. } => {
+                let mut map = Map::new();
+
+                for (key, value) in entries.iter() {
+                    let key = self.evaluate_expr(key)?;
+                    let key = MapKey::new(&key).map_err(|message| RuntimeError::new(brace, message))?;
+                    map.insert(key, self.evaluate_expr(value)?);
+                }
+
+                Ok(Box::new(Object::Map(Heap::map(map))))
+            },
+            Expr::Set { object, name, value } => {
+                match *self.evaluate_expr(object)? {
+                    Object::Instance(instance) => {
+                        let value = self.evaluate_expr(value)?;
+                        instance.borrow_mut().set(name, value.to_owned());
+                        Ok(value)
+                    },
+                    _ => Interpreter::runtime_error(name, String::from("Only instances have fields.")),
+                }
+            },
+            Expr::This { id, keyword } => self.look_up_variable(*id, keyword),
+            Expr::Super { id, keyword, method } => {
+                let distance = *self.locals.get(id).expect("Unresolved 'super' expression.");
+                let superclass = Environment::get_at(&self.environment, distance, keyword.to_owned())?;
+                let instance = Environment::get_at(&self.environment, distance - 1, Token::with_lexeme(String::from("this"), TokenType::This))?;
+
+                match *superclass {
+                    Object::Class(superclass) => {
+                        match superclass.find_method(&method.lexeme) {
+                            Some(function) => class::bind(&function, instance),
+                            None => Interpreter::runtime_error(method, format!("Undefined property '{}'.", method.lexeme)),
+                        }
+                    },
+                    _ => Interpreter::runtime_error(keyword, String::from("Superclass must be a class.")),
+                }
             },
             Expr::Logical { left, operator, right } => {
-                let left = self.evaluate_expr(left);
+                let left = self.evaluate_expr(left)?;
 
                 if operator.token_type == TokenType::Or{
-                    if left.to_owned().is_thuthy() {
-                        return left;
-                    }
-                } else {
-                    if !left.to_owned().is_thuthy(){
-                        return left;
+                    if left.is_thuthy() {
+                        return Ok(left);
                     }
+                } else if !left.is_thuthy(){
+                    return Ok(left);
                 }
 
                 self.evaluate_expr(right)
             },
-            Expr::Variable { name } => {
-                self.environment.borrow_mut().get(name.to_owned())
+            Expr::Variable { id, name } => self.look_up_variable(*id, name),
+            Expr::Interpolation { parts } => {
+                let mut value = String::new();
+
+                for part in parts.iter() {
+                    value.push_str(&self.evaluate_expr(part)?.to_string());
+                }
+
+                Ok(Box::new(Object::String(value)))
             },
-            Expr::Literal { value } => value.to_owned(),
-            Expr::Grouping { expression } => self.evaluate_expr(expression),
+            Expr::Literal { value, .. } => Ok(value.to_owned()),
+            Expr::Grouping { expression, .. } => self.evaluate_expr(expression),
             Expr::Unary { operator, right } => {
-                let right = self.evaluate_expr(right);
+                let right = self.evaluate_expr(right)?;
 
                 match operator.token_type {
                     TokenType::Bang => {
-                        Box::new(Object::Boolean(!right.is_thuthy()))
+                        Ok(Box::new(Object::Boolean(!right.is_thuthy())))
                     },
                     TokenType::Minus => match *right{
-                        Object::Number(num) => Box::new(Object::Number(-num)),
+                        Object::Number(num) => Ok(Box::new(Object::Number(-num))),
                         _ => Interpreter::runtime_error(operator, "Operand must be a number.".to_string()),
                     } ,
-                    _ => Box::new(Object::Nil)
+                    _ => Ok(Box::new(Object::Nil))
                 }
             },
             Expr::Binary { left, operator, right } => {
-                let left = self.evaluate_expr(left);
-                let right = self.evaluate_expr(right);
-
-                match (*left, *right) {
-                    (Object::String(str1), Object::String(str2)) => {
-                        match operator.token_type{
-                            TokenType::Plus => Box::new(Object::String(str1 + &str2)),
-                            TokenType::Slash | TokenType::Star | TokenType::Minus => Interpreter::runtime_error(operator, "Operands must be numbers.".to_string()),
-                            TokenType::BangEqual => Box::new(Object::Boolean(str1 != str2)),
-                            TokenType::EqualEqual => Box::new(Object::Boolean(str1 == str2)),
-                            _ => Box::new(Object::Nil)
-                        }
-                    },
-                    (Object::Number(num1), Object::Number(num2)) => {
-                        match operator.token_type {
-                            TokenType::Plus => Box::new(Object::Number(num1 + num2)),
-                            TokenType::Minus => Box::new(Object::Number(num1 - num2)),
-                            TokenType::Slash => Box::new(Object::Number(num1 / num2)),
-                            TokenType::Star => Box::new(Object::Number(num1 * num2)),
-                            TokenType::Greater => Box::new(Object::Boolean(num1 > num2)),
-                            TokenType::GreaterEqual => Box::new(Object::Boolean(num1 >= num2)),
-                            TokenType::Less => Box::new(Object::Boolean(num1 < num2)),
-                            TokenType::LessEqual => Box::new(Object::Boolean(num1 <= num2)),
-                            TokenType::BangEqual => Box::new(Object::Boolean(num1 != num2)),
-                            TokenType::EqualEqual => Box::new(Object::Boolean(num1 == num2)),
-                            _ => Box::new(Object::Number(0.0))
-                        }
-                    },
-                    (val1, val2) => {
-                        match operator.token_type {
-                            TokenType::Greater | TokenType::GreaterEqual | TokenType::Less | TokenType::LessEqual |
-                            TokenType::Slash | TokenType::Star | TokenType::Minus => Interpreter::runtime_error(operator, "Operands must be numbers.".to_string()),
-                            TokenType::Plus => Interpreter::runtime_error(operator, "Operands must be two numbers or two strings.".to_string()),
-                            TokenType::BangEqual => Box::new(Object::Boolean(!val1.is_equal(val2))),
-                            TokenType::EqualEqual => Box::new(Object::Boolean(val1.is_equal(val2))),
-                            _ => Box::new(Object::Nil)
-                        }
-                    }
-                }
+                let left = self.evaluate_expr(left)?;
+                let right = self.evaluate_expr(right)?;
+
+                Object::binary(*left, &operator.token_type, *right)
+                    .map(Box::new)
+                    .map_err(|message| RuntimeError::new(operator, message))
             },
-            _ => Box::new(Object::Nil)
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,19 @@
+pub mod token;
+pub mod scanner;
+mod char_extensions;
+mod unicode_tables;
+pub mod error;
+pub mod expression;
+pub mod statement;
+pub mod parser;
+pub mod ast_printer;
+pub mod formatter;
+pub mod interpreter;
+pub mod environment;
+pub mod returner;
+pub mod resolver;
+pub mod vm;
+mod lox;
+
+pub use environment::Object as Value;
+pub use lox::{Lox, LoxError};
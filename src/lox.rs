@@ -0,0 +1,113 @@
+use std::{fmt::Display, io::Write};
+
+use crate::{environment::{environment::MutEnv, BuiltinSignature}, Value, error::{Diagnostic, RuntimeError}, interpreter::Interpreter, parser::Parser, resolver::Resolver, scanner::Scanner, statement::Stmt};
+
+/// Embeddable Lox session. Globals persist between calls to [`Lox::run_source`].
+///
+/// Scripts run on the calling thread and recurse natively for every call.
+/// Run sessions on a thread with a stack of
+/// [`stack_size`](crate::interpreter::stack_size) bytes for the call depth
+/// limit, so deep recursion fails with a `Stack overflow.` runtime error
+/// rather than aborting the process.
+pub struct Lox {
+    interpreter: Interpreter,
+}
+
+#[derive(Debug)]
+pub enum LoxError {
+    /// The source failed to scan, parse or resolve, for the reasons given.
+    Compile(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+}
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoxError::Compile(errors) => {
+                let lines: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            },
+            LoxError::Runtime(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for LoxError {}
+
+impl From<RuntimeError> for LoxError {
+    fn from(error: RuntimeError) -> Self {
+        LoxError::Runtime(error)
+    }
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Lox {
+    pub fn new() -> Self {
+        Self { interpreter: Interpreter::new() }
+    }
+
+    /// Creates a session whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(output: impl Write + 'static) -> Self {
+        let mut lox = Self::new();
+        lox.interpreter.set_output(Box::new(output));
+        lox
+    }
+
+    /// Limits how deeply calls may nest, see [`Interpreter::set_max_depth`].
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.interpreter.set_max_depth(max_depth);
+    }
+
+    pub fn globals(&self) -> &MutEnv {
+        &self.interpreter.globals
+    }
+
+    /// Exposes a host function to scripts as a global named `name`. Like the
+    /// other builtins, scripts may declare their own global of that name.
+    pub fn register(&mut self, name: &str, arity: usize, function: BuiltinSignature) {
+        self.interpreter.prelude.borrow_mut().define_builtin(name, arity, function);
+    }
+
+    /// Runs a program and returns the value of its final statement when that
+    /// statement is an expression, or `nil` otherwise.
+    pub fn run_source(&mut self, source: &str) -> Result<Value, LoxError> {
+        let mut scanner = Scanner::new(source.to_owned());
+        scanner.scan_tokens();
+
+        let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
+        let stmts = parser.parse_program();
+
+        let mut errors = scanner.errors;
+        errors.extend(parser.diagnostics());
+        if !errors.is_empty() {
+            return Err(LoxError::Compile(errors));
+        }
+
+        let mut resolver = Resolver::new(&mut self.interpreter);
+        resolver.resolve(&stmts);
+
+        if !resolver.errors().is_empty() {
+            return Err(LoxError::Compile(resolver.errors().to_vec()));
+        }
+
+        match stmts.split_last() {
+            Some((last, rest)) => {
+                self.interpreter.interpret(rest)?;
+
+                match last.as_ref() {
+                    Stmt::Expression { expression, .. } => Ok(*self.interpreter.evaluate(expression)?),
+                    _ => {
+                        self.interpreter.interpret(std::slice::from_ref(last))?;
+                        Ok(Value::Nil)
+                    }
+                }
+            },
+            None => Ok(Value::Nil),
+        }
+    }
+}
@@ -1,41 +1,35 @@
-mod token;
-mod scanner;
-mod char_extensions;
-mod string_extensions;
-mod error;
-mod expression;
-mod statement;
-mod parser;
-mod ast_printer;
-mod interpreter;
-mod environment;
-mod returner;
-
-use std::{env, fs, io::{self, Write}};
-use ast_printer::AstPrinter;
-use error::ErrorHandler;
-use interpreter::Interpreter;
-use parser::Parser;
-use scanner::Scanner;
-use statement::Stmt;
+mod repl;
+
+use std::{env, fs};
+use interpreter_starter_rust::{ast_printer::AstPrinter, environment::heap::{GcConfig, Heap}, error::{Diagnostic, ErrorHandler}, formatter::Formatter, interpreter::{stack_size, Interpreter, DEFAULT_MAX_DEPTH}, parser::Parser, resolver::Resolver, scanner::Scanner, statement::Stmt, vm::{disassembler::Disassembler, Vm}, Lox, LoxError};
+use repl::Repl;
 
 
 fn read_file(filename: &String) -> String {
     fs::read_to_string(filename).unwrap_or_else(|_| {
-        writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
+        eprintln!("Failed to read file {}", filename);
         String::new()
     })
 }
 
+/// Reports `errors` and exits with `exit_code` if there are any.
+fn exit_on_errors(errors: &[Diagnostic], source: &str, exit_code: i32) {
+    if !errors.is_empty() {
+        ErrorHandler::report(errors, source);
+        std::process::exit(exit_code)
+    }
+}
+
 fn tokenize(filename: &String){
     let file_contents = read_file(filename);
 
     let mut exit_code = 0;
 
-    let mut scanner = Scanner::new(file_contents);
+    let mut scanner = Scanner::new(file_contents.clone());
     scanner.scan_tokens();
 
-    if ErrorHandler::had_error(){
+    if !scanner.errors.is_empty(){
+        ErrorHandler::report(&scanner.errors, &file_contents);
         exit_code = 65;
     }
 
@@ -49,84 +43,235 @@ fn tokenize(filename: &String){
 fn parse(filename: &String){
     let file_contents = read_file(filename);
 
-    let mut scanner = Scanner::new(file_contents);
+    let mut scanner = Scanner::new(file_contents.clone());
     scanner.scan_tokens();
 
     let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
     let expr = parser.parse_expr();
 
-    if ErrorHandler::had_error(){
-        std::process::exit(65)
-    }
+    let mut errors = scanner.errors;
+    errors.extend(parser.diagnostics());
+    exit_on_errors(&errors, &file_contents, 65);
 
-    match expr {
-        Some(e) => println!("{}", AstPrinter::new().print(e)),
-        _ => {},
+    if let Some(e) = expr {
+        println!("{}", AstPrinter::new().print(&e))
     }
 }
 
+fn parse_program(filename: &String){
+    let file_contents = read_file(filename);
+
+    let mut scanner = Scanner::new(file_contents.clone());
+    scanner.scan_tokens();
+
+    let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
+    let stmts = parser.parse_program();
+
+    let mut errors = scanner.errors;
+    errors.extend(parser.diagnostics());
+    exit_on_errors(&errors, &file_contents, 65);
+
+    println!("{}", AstPrinter::new().print_program(&stmts))
+}
+
+fn format(filename: &String){
+    let file_contents = read_file(filename);
+
+    let mut scanner = Scanner::new(file_contents.clone());
+    scanner.scan_tokens();
+
+    let mut parser = Parser::new(scanner.tokens.into_boxed_slice()).with_comments();
+    let stmts = parser.parse_program();
+
+    let mut errors = scanner.errors;
+    errors.extend(parser.diagnostics());
+    exit_on_errors(&errors, &file_contents, 65);
+
+    print!("{}", Formatter::new().format(&stmts))
+}
+
 fn evaluate(filename: &String) {
     let file_contents = read_file(filename);
 
-    let mut scanner = Scanner::new(file_contents);
+    let mut scanner = Scanner::new(file_contents.clone());
     scanner.scan_tokens();
 
     let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
     let stmts = parser.parse_stmt();
     
-    if ErrorHandler::had_error(){
-        std::process::exit(70)
-    }
+    let mut errors = scanner.errors;
+    errors.extend(parser.diagnostics());
+    exit_on_errors(&errors, &file_contents, 70);
     
     //println!("Tokens:\n{:?}", scanner.tokens);
 
     let mut interpreter = Interpreter::new();
-    
+    let mut resolver = Resolver::new(&mut interpreter);
+    resolver.resolve(&stmts);
+    exit_on_errors(resolver.errors(), &file_contents, 65);
+
     // If expression check
-    if parser.is_expression() && stmts.len() >= 1{
-        let expr = stmts.get(0).unwrap().as_ref();
-
-        match expr {
-            Stmt::Expression { expression } => {
-                let value = interpreter.evaluate_expr(expression);
-                
-                if ErrorHandler::had_error(){
-                    std::process::exit(65)
+    if parser.is_expression() && !stmts.is_empty(){
+        if let Stmt::Expression { expression, .. } = stmts[0].as_ref() {
+            match interpreter.evaluate(expression) {
+                Ok(value) => println!("{}", value),
+                Err(error) => {
+                    ErrorHandler::runtime_error(&error, &file_contents);
+                    std::process::exit(70)
                 }
-    
-                println!("{}", value.to_string());
-                return;
-            },
-            _ => {}
+            }
+            return;
         }
     }
     
     //println!("Statements:\n{:?}", stmts);
 
     // If statements
-    for stmt in stmts.iter(){
-        _ = interpreter.evaluate_stmt(&stmt);
-    } 
+    if let Err(error) = interpreter.interpret(&stmts) {
+        ErrorHandler::runtime_error(&error, &file_contents);
+        std::process::exit(70)
+    }
+}
+
+/// Execution backends selectable with `--backend`.
+const BACKENDS: [&str; 2] = ["tree", "vm"];
+
+fn run(filename: &String, backend: &str, trace: bool, gc: GcConfig, max_depth: usize) {
+    let file_contents = read_file(filename);
+
+    Heap::configure(gc);
+
+    let result = match backend {
+        "vm" => {
+            let mut vm = Vm::new();
+            vm.set_trace(trace);
+            vm.set_max_depth(max_depth);
+            vm.run_source(&file_contents)
+        },
+        _ => {
+            let mut lox = Lox::new();
+            lox.set_max_depth(max_depth);
+            lox.run_source(&file_contents).map(|_| ())
+        },
+    };
+
+    match result {
+        Ok(()) => {},
+        Err(LoxError::Compile(errors)) => exit_on_errors(&errors, &file_contents, 65),
+        Err(LoxError::Runtime(error)) => {
+            ErrorHandler::runtime_error(&error, &file_contents);
+            std::process::exit(70)
+        }
+    }
+}
+
+fn disassemble(filename: &String) {
+    let file_contents = read_file(filename);
+
+    match Vm::compile(&file_contents) {
+        Ok(function) => print!("{}", Disassembler::new().disassemble(&function)),
+        Err(LoxError::Compile(errors)) => exit_on_errors(&errors, &file_contents, 65),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(65)
+        }
+    }
+}
+
+/// Runs `task` on a thread with a native stack deep enough for the
+/// tree-walker to reach `max_depth` nested calls.
+fn with_call_stack(max_depth: usize, task: impl FnOnce() + Send + 'static) {
+    let Some(size) = stack_size(max_depth) else {
+        eprintln!("--max-depth {} needs more than 1 GiB of native stack for the tree backend.", max_depth);
+        std::process::exit(64)
+    };
+
+    let thread = std::thread::Builder::new().stack_size(size).spawn(task);
+
+    match thread {
+        Ok(thread) => if thread.join().is_err() {
+            std::process::exit(101)
+        },
+        Err(error) => {
+            eprintln!("Failed to start the interpreter: {}", error);
+            std::process::exit(70)
+        }
+    }
+}
+
+/// Value given to a flag such as `--backend vm`.
+fn flag_value<'a>(flags: &'a [String], name: &str) -> Option<&'a str> {
+    let position = flags.iter().position(|flag| flag == name)?;
+    flags.get(position + 1).map(|value| value.as_str())
 }
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    if args.len() < 2 {
+        with_call_stack(DEFAULT_MAX_DEPTH, || Repl::new().run());
+        return;
+    }
+
     if args.len() < 3 {
-        writeln!(io::stderr(), "Usage: {} tokenize <filename>", args[0]).unwrap();
+        eprintln!("Usage: {} <tokenize|parse [--program]|fmt|evaluate|run [--backend tree|vm] [--trace] [--gc-threshold <bytes>] [--gc-growth <factor>] [--max-depth <calls>]|disasm> <filename>", args[0]);
         return;
     }
 
     let command = &args[1];
-    let filename = &args[2];
+    let filename = &args[args.len() - 1];
+    let flags = &args[2..args.len() - 1];
 
     match command.as_str() {
         "tokenize" => tokenize(filename),
+        "parse" if flags.iter().any(|flag| flag == "--program") => parse_program(filename),
         "parse" => parse(filename),
-        "evaluate" => evaluate(filename),
+        "fmt" => format(filename),
+        "evaluate" => {
+            let filename = filename.to_owned();
+            with_call_stack(DEFAULT_MAX_DEPTH, move || evaluate(&filename))
+        },
+        "run" => {
+            let backend = flag_value(flags, "--backend").unwrap_or("tree");
+
+            if !BACKENDS.contains(&backend) {
+                eprintln!("Unknown backend: {}", backend);
+                std::process::exit(64)
+            }
+
+            let mut gc = GcConfig::default();
+            let mut max_depth = DEFAULT_MAX_DEPTH;
+
+            for (flag, setting) in [
+                ("--gc-threshold", &mut gc.initial_threshold),
+                ("--gc-growth", &mut gc.growth_factor),
+                ("--max-depth", &mut max_depth),
+            ] {
+                if let Some(value) = flag_value(flags, flag) {
+                    match value.parse() {
+                        Ok(value) => *setting = value,
+                        Err(_) => {
+                            eprintln!("Invalid value for {}: {}", flag, value);
+                            std::process::exit(64)
+                        }
+                    }
+                }
+            }
+
+            let trace = flags.iter().any(|flag| flag == "--trace");
+
+            match backend {
+                // The bytecode VM keeps its call frames on the heap.
+                "vm" => run(filename, backend, trace, gc, max_depth),
+                _ => {
+                    let filename = filename.to_owned();
+                    with_call_stack(max_depth, move || run(&filename, "tree", trace, gc, max_depth))
+                },
+            }
+        },
+        "disasm" => disassemble(filename),
         _ => {
-            writeln!(io::stderr(), "Unknown command: {}", command).unwrap();
-            return;
+            eprintln!("Unknown command: {}", command);
         }
     }
 }
@@ -1,148 +1,199 @@
-use std::fmt::format;
+use crate::{environment::Object, error::{Diagnostic, ParseError}, expression::Expr, statement::{LoopSyntax, Stmt}, token::{Comment, Span, Token, TokenType}};
 
-use crate::{environment::Object, error::{ErrorHandler, ParserError}, expression::Expr, statement::Stmt, token::{Token, TokenType}};
-
-pub type MyResult<T> = std::result::Result<Box<T>, ParserError>;
+pub type MyResult<T> = std::result::Result<Box<T>, ParseError>;
 
 pub struct Parser{
     tokens: Box<[Token]>,
     current: usize,
-    is_expression: bool
+    is_expression: bool,
+    keep_comments: bool,
+    /// Number of enclosing blocks, so recovery can stop at their closing brace.
+    block_depth: usize,
+    /// Number of loops around the statement being parsed, reset inside functions.
+    loop_depth: usize,
+    errors: Vec<ParseError>
 }
 
 impl Parser {
     pub fn new(tokens: Box<[Token]>) -> Self {
-        Self { tokens, current: 0, is_expression: true}
+        Self { tokens, current: 0, is_expression: true, keep_comments: false, block_depth: 0, loop_depth: 0, errors: Vec::new() }
+    }
+
+    /// Removes the doc comments in front of the token at `index` and joins
+    /// their text.
+    fn take_doc(&mut self, index: usize) -> Option<String> {
+        let comments = &mut self.tokens[index].comments;
+
+        let lines = comments.iter()
+            .filter(|comment| comment.doc)
+            .map(|comment| {
+                let body = &comment.text["///".len()..];
+                body.strip_prefix(' ').unwrap_or(body).trim_end().to_owned()
+            })
+            .collect::<Vec<String>>();
+
+        comments.retain(|comment| !comment.doc);
+
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
+
+    /// Keeps source comments in the tree as `Stmt::Comment` statements.
+    pub fn with_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
     }
 
     pub fn is_expression(&self) -> bool {
         self.is_expression
     }
 
+    /// Every syntax error reported so far. Parsing continues past errors, so
+    /// the parsed program holds all the statements that did parse.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// The syntax errors as diagnostics to report.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors.iter().map(Diagnostic::from).collect()
+    }
+
     fn expression(&mut self) -> MyResult<Expr> {
         self.assignment()
     }
 
     fn assignment(&mut self) -> MyResult<Expr> {
-        let expr = self.or();
+        let expr = self.or()?;
 
         if self.match_single(TokenType::Equal){
             let equals = self.previous().to_owned();
             let value = self.assignment()?;
 
             match expr.as_ref() {
-                Ok(ok) => {
-                    match ok.as_ref() {
-                        Expr::Variable { name } => {
-                            return Expr::Assign { name: name.to_owned(), value }.wrap()
-                        },
-                        _ => {
-                            self.error(equals, String::from("Invalid assignment target."));
-                        }
-                    }
+                Expr::Variable { name, .. } => {
+                    return Expr::Assign { id: Expr::next_id(), name: name.to_owned(), value }.wrap()
+                },
+                Expr::Get { object, name } => {
+                    return Expr::Set { object: object.to_owned(), name: name.to_owned(), value }.wrap()
+                },
+                Expr::Index { object, bracket, index } => {
+                    return Expr::SetIndex { object: object.to_owned(), bracket: bracket.to_owned(), index: index.to_owned(), value }.wrap()
                 },
-                Err(e) => return Err(e.clone())
+                _ => {
+                    self.error(equals, String::from("Invalid assignment target."));
+                }
             }
         }
 
-        expr
+        Ok(expr)
     }
 
     fn or(&mut self) -> MyResult<Expr> {
-        let mut expr = self.and();
+        let mut expr = self.and()?;
 
         while self.match_single(TokenType::Or) {
             let operator = self.previous().to_owned();
             let right = self.and()?;
-            expr = Expr::Logical { left: expr?, operator, right }.wrap();
+            expr = Box::new(Expr::Logical { left: expr, operator, right });
         }
 
-        expr
+        Ok(expr)
     }
 
     fn and(&mut self) -> MyResult<Expr> {
-        let mut expr = self.equality();
-        
+        let mut expr = self.equality()?;
+
         while self.match_single(TokenType::And) {
             let operator = self.previous().to_owned();
             let right = self.equality()?;
-            expr = Expr::Logical { left: expr?, operator, right }.wrap();
+            expr = Box::new(Expr::Logical { left: expr, operator, right });
         }
 
-        expr
+        Ok(expr)
     }
 
     fn equality(&mut self) -> MyResult<Expr> {
-        let mut expr = self.comparsion();
+        let mut expr = self.comparsion()?;
 
-        while self.match_many(Box::new([TokenType::BangEqual,TokenType::EqualEqual])) {
+        while self.match_many(&[TokenType::BangEqual,TokenType::EqualEqual]) {
             let operator = self.previous().to_owned();
             let right = self.comparsion()?;
 
-            expr = Expr::Binary { left: expr?, operator, right}.wrap()
+            expr = Box::new(Expr::Binary { left: expr, operator, right});
         }
 
-        return expr;
+        Ok(expr)
     }
 
     fn comparsion(&mut self) -> MyResult<Expr> {
-        let mut expr = self.term();
+        let mut expr = self.term()?;
 
-        while self.match_many(Box::new([TokenType::Greater,TokenType::GreaterEqual, TokenType::Less,TokenType::LessEqual])) {
+        while self.match_many(&[TokenType::Greater,TokenType::GreaterEqual, TokenType::Less,TokenType::LessEqual]) {
             let operator = self.previous().to_owned();
             let right = self.term()?;
 
-            expr = Expr::Binary { left: expr?, operator, right}.wrap()
+            expr = Box::new(Expr::Binary { left: expr, operator, right});
         }
 
-        return expr;
+        Ok(expr)
     }
 
     fn term(&mut self) -> MyResult<Expr> {
-        let mut expr = self.factor();
+        let mut expr = self.factor()?;
 
-        while self.match_many(Box::new([TokenType::Minus,TokenType::Plus])) {
+        while self.match_many(&[TokenType::Minus,TokenType::Plus]) {
             let operator = self.previous().to_owned();
             let right = self.factor()?;
 
-            expr = Expr::Binary { left: expr?, operator, right}.wrap()
+            expr = Box::new(Expr::Binary { left: expr, operator, right});
         }
 
-        return expr;
+        Ok(expr)
     }
 
     fn factor(&mut self) -> MyResult<Expr> {
-        let mut expr = self.unary();
+        let mut expr = self.unary()?;
 
-        while self.match_many(Box::new([TokenType::Slash,TokenType::Star])) {
+        while self.match_many(&[TokenType::Slash,TokenType::Star]) {
             let operator = self.previous().to_owned();
             let right = self.unary()?;
 
-            expr = Expr::Binary { left: expr?, operator, right}.wrap()
+            expr = Box::new(Expr::Binary { left: expr, operator, right});
         }
 
-        return expr;
+        Ok(expr)
     }
 
     fn unary(&mut self) -> MyResult<Expr> {
-        if self.match_many(Box::new([TokenType::Bang,TokenType::Minus])) {
+        if self.match_many(&[TokenType::Bang,TokenType::Minus]) {
             let operator = self.previous().to_owned();
             let right = self.unary()?;
 
             return Expr::Unary { operator, right }.wrap()
         }
 
-        return self.call();
+        self.call()
     }
 
     fn call(&mut self) -> MyResult<Expr> {
-        let mut expr = self.primary();
+        let mut expr = self.primary()?;
 
         loop {
             if self.match_single(TokenType::LeftParen){
-                expr = self.finish_call(expr?);
+                expr = self.finish_call(expr)?;
+            } else if self.match_single(TokenType::Dot){
+                let name = self.consume(&TokenType::Identifier, String::from("Expect property name after '.'."))?.to_owned();
+                expr = Box::new(Expr::Get { object: expr, name });
+            } else if self.match_single(TokenType::LeftBracket){
+                let index = self.expression()?;
+                let bracket = self.consume(&TokenType::RightBracket, String::from("Expect ']' after index."))?.to_owned();
+                expr = Box::new(Expr::Index { object: expr, bracket, index });
             } else {
-                break expr;
+                break Ok(expr);
             }
         }
     }
@@ -153,48 +204,160 @@ impl Parser {
         if !self.check(&TokenType::RightParen){
             arguments.push(self.expression()?);
             while self.match_single(TokenType::Comma) {
-                if arguments.len() > 255 {
+                if arguments.len() >= 255 {
                     self.error(self.peek().to_owned(), String::from("Can't have more than 255 arguments."));
                 }
                 arguments.push(self.expression()?);
             }
         }
 
-        let paren = self.consume(&TokenType::RightParen, String::from("Expect ')' after argumetns."));
+        let paren = self.consume(&TokenType::RightParen, String::from("Expect ')' after argumetns."))?.to_owned();
 
-        Expr::Call { callee, paren: paren?.to_owned(), arguments: arguments.into_boxed_slice() }.wrap()
+        Expr::Call { callee, paren, arguments: arguments.into_boxed_slice() }.wrap()
     }
 
     fn primary(&mut self) -> MyResult<Expr> {
         if self.match_single(TokenType::False){
-            return Expr::Literal { value: Box::new(Object::Boolean(false)) }.wrap()
+            return Expr::Literal { value: Box::new(Object::Boolean(false)), lexeme: None, span: self.previous().span() }.wrap()
         }
         if self.match_single(TokenType::True){
-            return Expr::Literal { value: Box::new(Object::Boolean(true)) }.wrap()
+            return Expr::Literal { value: Box::new(Object::Boolean(true)), lexeme: None, span: self.previous().span() }.wrap()
         }
         if self.match_single(TokenType::Nil){
-            return Expr::Literal { value: Box::new(Object::Nil) }.wrap()
+            return Expr::Literal { value: Box::new(Object::Nil), lexeme: None, span: self.previous().span() }.wrap()
+        }
+        if self.match_single(TokenType::Number) {
+            let previous = self.previous();
+            return Expr::Literal { value: previous.literal.to_owned(), lexeme: Some(previous.lexeme.to_owned()), span: previous.span() }.wrap()
+        }
+        if self.match_single(TokenType::String) {
+            let previous = self.previous();
+            return Expr::Literal { value: previous.literal.to_owned(), lexeme: None, span: previous.span() }.wrap()
+        }
+
+        if self.match_single(TokenType::Interpolation) {
+            return self.interpolation()
         }
-        if self.match_many(Box::new([TokenType::Number,TokenType::String])) {
-            return Expr::Literal { value:  self.previous().literal.to_owned() }.wrap()
+
+        if self.match_single(TokenType::Super) {
+            let keyword = self.previous().to_owned();
+            self.consume(&TokenType::Dot, String::from("Expect '.' after 'super'."))?;
+            let method = self.consume(&TokenType::Identifier, String::from("Expect superclass method name."))?.to_owned();
+            return Expr::Super { id: Expr::next_id(), keyword, method }.wrap()
+        }
+
+        if self.match_single(TokenType::This) {
+            return Expr::This { id: Expr::next_id(), keyword: self.previous().to_owned() }.wrap()
         }
 
         if self.match_single(TokenType::Identifier) {
-            return Expr::Variable { name: self.previous().to_owned() }.wrap()
+            return Expr::Variable { id: Expr::next_id(), name: self.previous().to_owned() }.wrap()
+        }
+
+        if self.match_single(TokenType::LeftBracket) {
+            return self.list()
+        }
+
+        if self.match_single(TokenType::LeftBrace) {
+            return self.map()
+        }
+
+        if self.match_single(TokenType::Fun) {
+            return self.lambda()
+        }
+
+        if self.starts_arrow() {
+            self.advance();
+            return self.arrow_function()
         }
 
         if self.match_single(TokenType::LeftParen) {
-            let expr = self.expression();
-            _ = self.consume(&TokenType::RightParen, String::from("Expect ')' after expression."));
-            return Expr::Grouping { expression: expr? }.wrap()
+            let start = self.previous().span();
+            let expr = self.expression()?;
+            self.consume(&TokenType::RightParen, String::from("Expect ')' after expression."))?;
+            return Expr::Grouping { expression: expr, span: self.span_from(start) }.wrap()
         }
 
         Err(self.error(self.peek().clone(), String::from("Expect expression.")))
     }
 
+    fn list(&mut self) -> MyResult<Expr> {
+        let start = self.previous().span();
+        let mut elements = vec![];
+
+        while !self.check(&TokenType::RightBracket) {
+            elements.push(self.expression()?);
+
+            if !self.match_single(TokenType::Comma) {
+                break;
+            }
+        }
+
+        self.consume(&TokenType::RightBracket, String::from("Expect ']' after list elements."))?;
+
+        Expr::List { elements: elements.into_boxed_slice(), span: self.span_from(start) }.wrap()
+    }
+
+    fn map(&mut self) -> MyResult<Expr> {
+        let brace = self.previous().to_owned();
+        let mut entries = vec![];
+
+        while !self.check(&TokenType::RightBrace) {
+            let key = self.expression()?;
+            self.consume(&TokenType::Colon, String::from("Expect ':' after map key."))?;
+            let value = self.expression()?;
+            entries.push((key, value));
+
+            if !self.match_single(TokenType::Comma) {
+                break;
+            }
+        }
+
+        self.consume(&TokenType::RightBrace, String::from("Expect '}' after map entries."))?;
+
+        let span = self.span_from(brace.span());
+        Expr::Map { brace, entries: entries.into_boxed_slice(), span }.wrap()
+    }
+
+    /// Whether the next `{` starts a map literal rather than a block, which
+    /// is when a single-token key and a colon follow it.
+    fn starts_map(&self) -> bool {
+        self.check(&TokenType::LeftBrace) && self.check_at(2, &TokenType::Colon)
+    }
+
+    fn interpolation(&mut self) -> MyResult<Expr> {
+        let mut parts = vec![];
+
+        loop {
+            let segment = self.previous();
+            let text = Expr::Literal { value: segment.literal.to_owned(), lexeme: None, span: segment.span() };
+            let last = segment.token_type == TokenType::InterpolationEnd;
+
+            parts.push(Box::new(text));
+
+            if last {
+                break;
+            }
+
+            parts.push(self.expression()?);
+
+            if !self.match_many(&[TokenType::InterpolationMid, TokenType::InterpolationEnd]) {
+                return Err(self.error(self.peek().to_owned(), String::from("Expect '}' after interpolated expression.")));
+            }
+        }
+
+        Expr::Interpolation { parts: parts.into_boxed_slice() }.wrap()
+    }
+
     fn declaration(&mut self) -> MyResult<Stmt> {
-        let result = if self.match_single(TokenType::Fun) {
-            self.function("function")
+        let result = if self.match_single(TokenType::Class) {
+            let doc = self.take_doc(self.current - 1);
+            self.class_declaration(doc)
+        }
+        // `fun` without a name starts an anonymous function expression.
+        else if self.check_at(1, &TokenType::Identifier) && self.match_single(TokenType::Fun) {
+            let doc = self.take_doc(self.current - 1);
+            self.function("function", doc)
         }
         else if self.match_single(TokenType::Var){
             self.var_declaration()
@@ -211,99 +374,276 @@ impl Parser {
         }
     }
 
-    fn add_parameter(&mut self, params: &mut Vec<Token>){
+    fn class_declaration(&mut self, doc: Option<String>) -> MyResult<Stmt> {
+        self.is_expression = false;
+        let start = self.previous().span();
+        let name = self.consume(&TokenType::Identifier, String::from("Expect class name."))?.to_owned();
+
+        let mut superclass = None;
+        if self.match_single(TokenType::Less){
+            self.consume(&TokenType::Identifier, String::from("Expect superclass name."))?;
+            superclass = Some(Box::new(Expr::Variable { id: Expr::next_id(), name: self.previous().to_owned() }));
+        }
+
+        self.consume(&TokenType::LeftBrace, String::from("Expect '{' before class body."))?;
+
+        let mut methods = vec![];
+        while !self.check(&TokenType::RightBrace) && !self.is_end() {
+            let doc = self.take_doc(self.current);
+            self.push_comments(self.current, &mut methods);
+
+            let start = self.current;
+            match self.function("method", doc) {
+                Ok(method) => methods.push(method),
+                // Already reported; the rest of the class is still parsed.
+                Err(_) => self.skip_method(),
+            }
+
+            for index in start + 1..self.current {
+                self.push_comments(index, &mut methods);
+            }
+        }
+
+        self.push_comments(self.current, &mut methods);
+
+        self.consume(&TokenType::RightBrace, String::from("Expect '}' after class body."))?;
+
+        Stmt::Class { name, superclass, methods: methods.into_boxed_slice(), doc, span: self.span_from(start) }.wrap()
+    }
+
+    fn add_parameter(&mut self, params: &mut Vec<Token>) -> Result<(), ParseError> {
         if params.len() >= 255{
             self.error(self.peek().to_owned(), String::from("Can't have more than 255 parameters."));
         }
-        let token = self.consume(&TokenType::Identifier, String::from("Expect parameter name.")); 
-        params.push(token.unwrap().to_owned());
+        let token = self.consume(&TokenType::Identifier, String::from("Expect parameter name."))?.to_owned();
+        params.push(token);
+        Ok(())
     }
 
-    fn function(&mut self, kind: &'static str) -> MyResult<Stmt> {
+    fn function(&mut self, kind: &'static str, doc: Option<String>) -> MyResult<Stmt> {
         self.is_expression = false;
-        let name = &self.consume(&TokenType::Identifier, format!("Expect {} name.", kind))?.to_owned();
 
-        _ = self.consume(&TokenType::LeftParen, format!("Expect '(' after {} name.", kind));
-        
+        // Methods have no `fun` keyword and start at their name.
+        let start = if self.previous().token_type == TokenType::Fun {
+            self.previous().span()
+        } else {
+            self.peek().span()
+        };
+
+        let name = self.consume(&TokenType::Identifier, format!("Expect {} name.", kind))?.to_owned();
+
+        self.consume(&TokenType::LeftParen, format!("Expect '(' after {} name.", kind))?;
+        let params = self.parameters()?;
+        let body = self.function_body(kind)?;
+
+        Stmt::Function { name, params, body, doc, span: self.span_from(start) }.wrap()
+    }
+
+    /// Parses parameter names up to the closing parenthesis.
+    fn parameters(&mut self) -> Result<Box<[Token]>, ParseError> {
         let mut params = vec![];
-        
+
         if !self.check(&TokenType::RightParen){
-            self.add_parameter(&mut params);
-            
+            self.add_parameter(&mut params)?;
+
             while self.match_single(TokenType::Comma) {
-                self.add_parameter(&mut params);
+                self.add_parameter(&mut params)?;
             }
         }
-        
-        _ = self.consume(&TokenType::RightParen, format!("Expect ')' after parameters."));
 
-        _ = self.consume(&TokenType::LeftBrace, format!("Expect '{{' before {} body.", kind));
+        self.consume(&TokenType::RightParen, String::from("Expect ')' after parameters."))?;
+
+        Ok(params.into_boxed_slice())
+    }
 
+    fn function_body(&mut self, kind: &str) -> Result<Box<[Box<Stmt>]>, ParseError> {
+        self.consume(&TokenType::LeftBrace, format!("Expect '{{' before {} body.", kind))?;
+
+        // Loops outside the function can't be exited from its body.
+        let loop_depth = std::mem::take(&mut self.loop_depth);
         let body = self.block();
+        self.loop_depth = loop_depth;
+
+        body
+    }
+
+    fn lambda(&mut self) -> MyResult<Expr> {
+        let keyword = self.previous().to_owned();
+
+        self.consume(&TokenType::LeftParen, String::from("Expect '(' after 'fun'."))?;
+        let params = self.parameters()?;
+        let body = self.function_body("function")?;
+
+        let span = self.span_from(keyword.span());
+        Expr::Lambda { keyword, params, body, span }.wrap()
+    }
+
+    /// Parses `(params) => expression` into a function returning the expression.
+    fn arrow_function(&mut self) -> MyResult<Expr> {
+        let start = self.previous().span();
+
+        let params = self.parameters()?;
+        let keyword = self.consume(&TokenType::Arrow, String::from("Expect '=>' after parameters."))?.to_owned();
+        let value = self.expression()?;
+
+        let body: Box<[Box<Stmt>]> = Box::new([Box::new(Stmt::Return {
+            span: keyword.span().to(value.span()),
+            keyword: keyword.to_owned(),
+            value: Some(value),
+        })]);
+
+        Expr::Lambda { keyword, params, body, span: self.span_from(start) }.wrap()
+    }
+
+    /// Whether the next `(` opens the parameter list of an arrow function.
+    fn starts_arrow(&self) -> bool {
+        if !self.check(&TokenType::LeftParen) {
+            return false;
+        }
+
+        let mut distance = 1;
+
+        if !self.check_at(distance, &TokenType::RightParen) {
+            loop {
+                if !self.check_at(distance, &TokenType::Identifier) {
+                    return false;
+                }
+
+                distance += 1;
+
+                if self.check_at(distance, &TokenType::RightParen) {
+                    break;
+                }
+
+                if !self.check_at(distance, &TokenType::Comma) {
+                    return false;
+                }
+
+                distance += 1;
+            }
+        }
 
-        Stmt::Function { name: name.to_owned(), params: params.into_boxed_slice(), body }.wrap()
+        self.check_at(distance + 1, &TokenType::Arrow)
     }
 
     fn var_declaration(&mut self) -> MyResult<Stmt> {
         self.is_expression = false;
+        let start = self.previous().span();
 
-        let name = self.consume(&TokenType::Identifier, String::from("Expect variable name.")).unwrap().to_owned();
+        let name = self.consume(&TokenType::Identifier, String::from("Expect variable name."))?.to_owned();
 
-        let mut initializer = Expr::Literal { value: Box::new(Object::Unitialized) }.wrap();
+        let mut initializer = Box::new(Expr::Literal { value: Box::new(Object::Unitialized), lexeme: None, span: name.span() });
 
         if self.match_single(TokenType::Equal){
-            initializer = self.expression();
+            initializer = self.expression()?;
         }
 
-        _ = self.consume(&TokenType::Semicolon, String::from("Expect ';' after variable declaration"));
+        self.consume(&TokenType::Semicolon, String::from("Expect ';' after variable declaration"))?;
 
-        Stmt::Var { name: name.to_owned(), initializer: initializer? }.wrap()
+        Stmt::Var { name, initializer, span: self.span_from(start) }.wrap()
     }
 
     pub fn parse_expr(&mut self) -> Option<Box<Expr>> {
-        match self.expression(){
-            Ok(expr) => Some(expr),
-            Err(_) => None,
-        }
+        self.expression().ok()
+    }
+
+    /// Parses the tokens as a whole program, where every expression
+    /// statement must be terminated by a semicolon.
+    pub fn parse_program(&mut self) -> Box<[Box<Stmt>]> {
+        self.is_expression = false;
+        self.parse_stmt()
     }
 
     pub fn parse_stmt(&mut self) -> Box<[Box<Stmt>]> {
         let mut stmts: Vec<Box<Stmt>> = Vec::new();
 
         while !self.is_end() {
-            match self.declaration() {
-                Ok(stmt) => stmts.push(stmt),
-                Err(_) => {},
-            }
+            _ = self.commented_declaration(&mut stmts);
         }
 
+        self.push_comments(self.current, &mut stmts);
+
         stmts.into_boxed_slice()
     }
 
-    fn block(&mut self) -> Box<[Box<Stmt>]>{
+    /// Parses a declaration into `stmts` together with the comments attached
+    /// to its tokens when the parser keeps comments.
+    #[allow(clippy::vec_box)]
+    fn commented_declaration(&mut self, stmts: &mut Vec<Box<Stmt>>) -> Result<(), ParseError> {
+        self.push_comments(self.current, stmts);
+
+        let start = self.current;
+        let result = self.declaration().map(|stmt| stmts.push(stmt));
+
+        for index in start + 1..self.current {
+            self.push_comments(index, stmts);
+        }
+
+        result
+    }
+
+    #[allow(clippy::vec_box)]
+    fn push_comments(&mut self, index: usize, stmts: &mut Vec<Box<Stmt>>) {
+        if !self.keep_comments {
+            return;
+        }
+
+        let previous_line = index.checked_sub(1).map(|previous| self.tokens[previous].line);
+
+        // Doc comments in front of a declaration are left for it to take.
+        let declaration = matches!(self.tokens[index].token_type, TokenType::Fun | TokenType::Class);
+        let comments = std::mem::take(&mut self.tokens[index].comments);
+        let (docs, comments): (Vec<Comment>, Vec<Comment>) = comments.into_iter()
+            .partition(|comment| comment.doc && declaration);
+        self.tokens[index].comments = docs;
+
+        for comment in comments {
+            let trailing = previous_line == Some(comment.span.line);
+            stmts.push(Box::new(Stmt::Comment { text: comment.text, trailing, span: comment.span }));
+        }
+    }
+
+    fn block(&mut self) -> Result<Box<[Box<Stmt>]>, ParseError>{
         let mut stmts = vec![];
 
-        while !self.check(&TokenType::RightBrace) 
+        self.block_depth += 1;
+
+        while !self.check(&TokenType::RightBrace)
             & !self.is_end() {
-            stmts.push(self.declaration().unwrap());
+            // A failed declaration has already been reported and skipped.
+            _ = self.commented_declaration(&mut stmts);
         }
 
-        _ = self.consume(&TokenType::RightBrace, String::from("Expect '}' after block."));
+        self.block_depth -= 1;
 
-        stmts.into_boxed_slice()
+        self.push_comments(self.current, &mut stmts);
+
+        self.consume(&TokenType::RightBrace, String::from("Expect '}' after block."))?;
+
+        Ok(stmts.into_boxed_slice())
     }
 
     fn statement(&mut self) -> MyResult<Stmt> {
+        if self.match_single(TokenType::Break){
+            self.is_expression = false;
+            return self.loop_jump_statement();
+        }
+
+        if self.match_single(TokenType::Continue){
+            self.is_expression = false;
+            return self.loop_jump_statement();
+        }
+
         if self.match_single(TokenType::For){
             self.is_expression = false;
             return self.for_statement();
         }
-        
+
         if self.match_single(TokenType::If){
             self.is_expression = false;
             return self.if_statement();
         }
-        
+
         if self.match_single(TokenType::Print){
             self.is_expression = false;
             return self.print_statement();
@@ -319,9 +659,11 @@ impl Parser {
             return self.while_statement();
         }
 
-        if self.match_single(TokenType::LeftBrace){
+        if !self.starts_map() && self.match_single(TokenType::LeftBrace){
             self.is_expression = false;
-            return Stmt::Block { statements: self.block() }.wrap();
+            let start = self.previous().span();
+            let statements = self.block()?;
+            return Stmt::Block { statements, span: self.span_from(start) }.wrap();
         }
 
         self.expression_statement()
@@ -336,29 +678,57 @@ impl Parser {
             value = Some(self.expression()?);
         }
 
-        _ = self.consume(&TokenType::Semicolon, String::from("Expect ';' after return value."));
+        self.consume(&TokenType::Semicolon, String::from("Expect ';' after return value."))?;
+
+        let span = self.span_from(keyword.span());
+        Stmt::Return { keyword, value, span }.wrap()
+    }
+
+    /// Parses `break;` or `continue;`, whose keyword was just consumed.
+    fn loop_jump_statement(&mut self) -> MyResult<Stmt> {
+        let keyword = self.previous().to_owned();
+
+        if self.loop_depth == 0 {
+            self.error(keyword.to_owned(), format!("Can't use '{}' outside of a loop.", keyword.lexeme));
+        }
+
+        self.consume(&TokenType::Semicolon, format!("Expect ';' after '{}'.", keyword.lexeme))?;
+
+        let span = self.span_from(keyword.span());
+        match keyword.token_type {
+            TokenType::Break => Stmt::Break { keyword, span }.wrap(),
+            _ => Stmt::Continue { keyword, span }.wrap(),
+        }
+    }
 
-        Stmt::Return { keyword, value }.wrap()
+    /// Parses a loop body, where `break` and `continue` are allowed.
+    fn loop_body(&mut self) -> MyResult<Stmt> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
     }
 
     fn if_statement(&mut self) -> MyResult<Stmt> {
-        _ = self.consume(&TokenType::LeftParen, String::from("Expect '(' after 'if'."));
-        let condition = self.expression();
-        _ = self.consume(&TokenType::RightParen, String::from("Expect ')' after condition."));
+        let start = self.previous().span();
+        self.consume(&TokenType::LeftParen, String::from("Expect '(' after 'if'."))?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, String::from("Expect ')' after condition."))?;
 
-        let then_branch = self.statement();
+        let then_branch = self.statement()?;
         let else_branch = if self.match_single(TokenType::Else){
             Some(self.statement()?)
         } else {
             None
         };
 
-        Stmt::If { condition:condition?, then_branch: then_branch?, else_branch }.wrap()
+        Stmt::If { condition, then_branch, else_branch, span: self.span_from(start) }.wrap()
     }
 
     fn for_statement(&mut self) -> MyResult<Stmt> {
-        _ = self.consume(&TokenType::LeftParen, String::from("Expect '(' after 'for'."));
-        
+        let start = self.previous().span();
+        self.consume(&TokenType::LeftParen, String::from("Expect '(' after 'for'."))?;
+
         let initializer = if self.match_single(TokenType::Semicolon){
             None
         } else if self.match_single(TokenType::Var) {
@@ -366,74 +736,73 @@ impl Parser {
         } else {
             Some(self.expression_statement()?)
         };
-        
+
         let mut condition: Option<Box<Expr>> = None;
         if !self.check(&TokenType::Semicolon){
             condition = Some(self.expression()?);
         };
-        
-        _ = self.consume(&TokenType::Semicolon, String::from("Expect ';' after loop condition."));
-        
+
+        self.consume(&TokenType::Semicolon, String::from("Expect ';' after loop condition."))?;
+
         let mut increment: Option<Box<Expr>> = None;
         if !self.check(&TokenType::RightParen){
             increment = Some(self.expression()?);
         };
-        
-        _ = self.consume(&TokenType::RightParen, String::from("Expect ')' after for clauses."));
 
-        let mut body = self.statement()?;
+        self.consume(&TokenType::RightParen, String::from("Expect ')' after for clauses."))?;
 
-        match increment {
-            Some(increment) => {
-                let statements: Box<[Box<Stmt>]> = Box::new([body, Box::new(Stmt::Expression { expression: increment })]);
-                body = Box::new(Stmt::Block { statements });
-            },
-            None => {},
-        }
+        let mut body = self.loop_body()?;
+
+        let syntax = LoopSyntax::For { initializer: initializer.is_some(), condition: condition.is_some() };
 
         if condition.is_none(){
-            condition = Some(Box::new(Expr::Literal { value: Box::new(Object::Boolean(true)) }));
+            condition = Some(Box::new(Expr::Literal { value: Box::new(Object::Boolean(true)), lexeme: None, span: Span::default() }));
         }
 
-        body = Box::new(Stmt::While { condition: condition.unwrap(), body });
+        let span = self.span_from(start);
+        body = Box::new(Stmt::While { condition: condition.unwrap(), body, increment, syntax, span });
 
-        match initializer{
-            Some(initializer) => {
-                let statements: Box<[Box<Stmt>]> = Box::new([initializer, body]);
-                body = Box::new(Stmt::Block { statements });
-            },
-            None => {},
+        if let Some(initializer) = initializer {
+            let statements: Box<[Box<Stmt>]> = Box::new([initializer, body]);
+            body = Box::new(Stmt::Block { statements, span });
         }
 
-        return body.wrap();
-
+        body.wrap()
     }
 
     fn while_statement(&mut self) -> MyResult<Stmt> {
-        _ = self.consume(&TokenType::LeftParen, String::from("Expect '(' after 'while'."));
-        let condition = self.expression();
-        _ = self.consume(&TokenType::RightParen, String::from("Expect ')' after condition."));
-        let body = self.statement();
-        
-        Stmt::While { condition: condition?, body: body? }.wrap()
+        let start = self.previous().span();
+        self.consume(&TokenType::LeftParen, String::from("Expect '(' after 'while'."))?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, String::from("Expect ')' after condition."))?;
+        let body = self.loop_body()?;
+
+        Stmt::While { condition, body, increment: None, syntax: LoopSyntax::While, span: self.span_from(start) }.wrap()
     }
 
     fn expression_statement(&mut self) -> MyResult<Stmt> {
-        let value = self.expression();
+        let start = self.peek().span();
+        let value = self.expression()?;
         if !self.is_expression{
-            _ = self.consume(&TokenType::Semicolon, String::from("Expect ';' after expression."));
+            self.consume(&TokenType::Semicolon, String::from("Expect ';' after expression."))?;
         }
-        Stmt::Expression { expression: value? }.wrap()
+        Stmt::Expression { expression: value, span: self.span_from(start) }.wrap()
     }
 
     fn print_statement(&mut self) -> MyResult<Stmt> {
         self.is_expression = false;
-        let value = self.expression();
-        _ = self.consume(&TokenType::Semicolon, String::from("Expect ';' after value."));
-        Stmt::Print { expression: value? }.wrap()
+        let start = self.previous().span();
+        let value = self.expression()?;
+        self.consume(&TokenType::Semicolon, String::from("Expect ';' after value."))?;
+        Stmt::Print { expression: value, span: self.span_from(start) }.wrap()
+    }
+
+    /// Span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span())
     }
 
-    fn consume(&mut self, token_type: &TokenType, message: String) -> Result<&Token, ParserError> {
+    fn consume(&mut self, token_type: &TokenType, message: String) -> Result<&Token, ParseError> {
         if self.check(token_type) {
             return Ok(self.advance());
         }
@@ -441,9 +810,10 @@ impl Parser {
         Err(self.error(self.peek().to_owned(), message))
     }
 
-    fn error(&mut self, token: Token, message: String) -> ParserError {
-        ErrorHandler::error_token(token, message);
-        ParserError::new()
+    fn error(&mut self, token: Token, message: String) -> ParseError {
+        let error = ParseError::new(&token, message);
+        self.errors.push(error.clone());
+        error
     }
 
     fn match_single(&mut self, token_type: TokenType) -> bool {
@@ -454,7 +824,7 @@ impl Parser {
         false
     }
 
-    fn match_many(&mut self, types: Box<[TokenType]>) -> bool {
+    fn match_many(&mut self, types: &[TokenType]) -> bool {
         for token_type in types.iter(){
             if self.match_single(token_type.to_owned()){
                 return true;
@@ -463,8 +833,14 @@ impl Parser {
         false
     }
 
+    /// Skips tokens up to the next statement boundary. Inside a block the
+    /// closing brace is left for the block to consume.
     fn synchronize(&mut self) {
-        self.advance();
+        let in_block = self.block_depth > 0;
+
+        if !(in_block && self.check(&TokenType::RightBrace)) {
+            self.advance();
+        }
 
         while !self.is_end() {
             if self.previous().token_type == TokenType::Semicolon{
@@ -472,6 +848,7 @@ impl Parser {
             }
 
             match self.peek().token_type {
+                TokenType::RightBrace if in_block => return,
                 TokenType::Class | TokenType::Fun |
                 TokenType::For   | TokenType::If |
                 TokenType::Print | TokenType::Return |
@@ -481,18 +858,48 @@ impl Parser {
         }
     }
 
+    /// Skips the rest of a method that failed to parse, up to the end of its
+    /// body or the `}` closing the class.
+    fn skip_method(&mut self) {
+        let mut depth = 0;
+
+        while !self.is_end() {
+            match self.peek().token_type {
+                TokenType::RightBrace if depth == 0 => return,
+                TokenType::RightBrace => {
+                    depth -= 1;
+                    self.advance();
+
+                    if depth == 0 {
+                        return;
+                    }
+                },
+                TokenType::LeftBrace => {
+                    depth += 1;
+                    self.advance();
+                },
+                _ => _ = self.advance(),
+            }
+        }
+    }
+
     fn check(&self, token_type: &TokenType) -> bool{
         if self.is_end() {
             return false;
         }
-        return self.peek().token_type == *token_type
+        self.peek().token_type == *token_type
+    }
+
+    /// Checks the type of the token `distance` tokens ahead of the current one.
+    fn check_at(&self, distance: usize, token_type: &TokenType) -> bool {
+        self.tokens.get(self.current + distance).is_some_and(|token| token.token_type == *token_type)
     }
 
     fn advance(&mut self) -> &Token{
         if !self.is_end() {
             self.current+=1;
         }
-        return self.previous();
+        self.previous()
     }
 
     fn is_end(&self) -> bool{
@@ -506,4 +913,4 @@ impl Parser {
     fn previous(&self) -> &Token {
         self.tokens.get(self.current - 1).unwrap()
     }
-}
\ No newline at end of file
+}
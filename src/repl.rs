@@ -0,0 +1,159 @@
+use std::io::{self, BufRead, Write};
+
+use interpreter_starter_rust::{error::ErrorHandler, interpreter::Interpreter, parser::Parser, resolver::Resolver, scanner::Scanner, statement::Stmt, token::TokenType};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+pub struct Repl {
+    interpreter: Interpreter,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self { interpreter: Interpreter::new() }
+    }
+
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        let mut buffer = String::new();
+
+        loop {
+            print!("{}", if buffer.is_empty() { PROMPT } else { CONTINUATION_PROMPT });
+            _ = io::stdout().flush();
+
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                _ => {
+                    println!();
+                    break;
+                }
+            };
+
+            buffer.push_str(&line);
+            buffer.push('\n');
+
+            if is_incomplete(&buffer) {
+                continue;
+            }
+
+            let source = std::mem::take(&mut buffer);
+            if !source.trim().is_empty() {
+                self.execute(source);
+            }
+        }
+    }
+
+    fn execute(&mut self, mut source: String) {
+        let mut scanner = Scanner::new(source.clone());
+        scanner.scan_tokens();
+
+        if !scanner.errors.is_empty() {
+            ErrorHandler::report(&scanner.errors, &source);
+            return;
+        }
+
+        let Some(last) = scanner.tokens.iter().rev().find(|token| token.token_type != TokenType::Eof) else {
+            return;
+        };
+        let end = last.span().offset + last.span().length;
+
+        let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
+        let mut stmts = parser.parse_program();
+
+        // A trailing semicolon is optional at the prompt: input whose only
+        // error is at its end is parsed again with one, and an expression
+        // typed that way has its value printed.
+        let unterminated = matches!(parser.errors(), [error] if error.token.token_type == TokenType::Eof);
+
+        if unterminated {
+            // Right after the last token, so errors point at the line typed.
+            source.insert(end, ';');
+            let mut scanner = Scanner::new(source.clone());
+            scanner.scan_tokens();
+            parser = Parser::new(scanner.tokens.into_boxed_slice());
+            stmts = parser.parse_program();
+        }
+
+        if !parser.errors().is_empty() {
+            ErrorHandler::report(&parser.diagnostics(), &source);
+            return;
+        }
+
+        let mut resolver = Resolver::new(&mut self.interpreter);
+        resolver.resolve(&stmts);
+
+        if !resolver.errors().is_empty() {
+            ErrorHandler::report(resolver.errors(), &source);
+            return;
+        }
+
+        if unterminated && stmts.len() == 1 {
+            if let Stmt::Expression { expression, .. } = stmts[0].as_ref() {
+                match self.interpreter.evaluate(expression) {
+                    Ok(value) => println!("{}", value),
+                    Err(error) => ErrorHandler::runtime_error(&error, &source),
+                }
+                return;
+            }
+        }
+
+        if let Err(error) = self.interpreter.interpret(&stmts) {
+            ErrorHandler::runtime_error(&error, &source);
+        }
+    }
+}
+
+/// Reports whether the input has unclosed parentheses, brackets, braces,
+/// strings or block comments and the prompt should keep reading lines.
+fn is_incomplete(source: &str) -> bool {
+    let mut depth: i32 = 0;
+    let mut in_string = false;
+    let mut comment_depth = 0;
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            match c {
+                '"' => in_string = false,
+                '\\' => _ = chars.next(),
+                _ => {}
+            }
+            continue;
+        }
+
+        if comment_depth > 0 {
+            match c {
+                '/' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    comment_depth += 1;
+                },
+                '*' if chars.peek() == Some(&'/') => {
+                    chars.next();
+                    comment_depth -= 1;
+                },
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                comment_depth += 1;
+            },
+            _ => {}
+        }
+    }
+
+    in_string || comment_depth > 0 || depth > 0
+}
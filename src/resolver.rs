@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+
+use crate::{error::Diagnostic, expression::{Expr, ExprVisitor}, interpreter::Interpreter, statement::{Stmt, StmtVisitor}, token::Token};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        Self {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Every error found in the statements resolved so far.
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(Diagnostic::at_token(token, message.to_owned()));
+    }
+
+    pub fn resolve(&mut self, statements: &[Box<Stmt>]) {
+        for stmt in statements.iter() {
+            self.resolve_stmt(stmt);
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        stmt.accept(self)
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        expr.accept(self)
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        let redeclared = scope.insert(name.lexeme.to_owned(), false).is_some();
+        if redeclared {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.to_owned(), true);
+        }
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), true);
+        }
+    }
+
+    fn resolve_local(&mut self, id: usize, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.lexeme) {
+                self.interpreter.resolve(id, depth);
+                return;
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Box<Stmt>], function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        self.begin_scope();
+        for param in params.iter() {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve(body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    fn resolve_class(&mut self, name: &Token, superclass: &Option<Box<Expr>>, methods: &[Box<Stmt>]) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            if let Expr::Variable { name: superclass_name, .. } = superclass.as_ref() {
+                if superclass_name.lexeme == name.lexeme {
+                    self.error(superclass_name, "A class can't inherit from itself.");
+                }
+            }
+
+            self.current_class = ClassType::Subclass;
+            self.resolve_expr(superclass);
+
+            self.begin_scope();
+            self.define_name("super");
+        }
+
+        self.begin_scope();
+        self.define_name("this");
+
+        for method in methods.iter() {
+            if let Stmt::Function { name, params, body, .. } = method.as_ref() {
+                let function_type = if name.lexeme == "init" {
+                    FunctionType::Initializer
+                } else {
+                    FunctionType::Method
+                };
+
+                self.resolve_function(params, body, function_type);
+            }
+        }
+
+        self.end_scope();
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+}
+
+impl StmtVisitor<()> for Resolver<'_> {
+    fn visit(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { statements, .. } => {
+                self.begin_scope();
+                self.resolve(statements);
+                self.end_scope();
+            },
+            Stmt::Class { name, superclass, methods, .. } => {
+                self.resolve_class(name, superclass, methods);
+            },
+            Stmt::Expression { expression, .. } => self.resolve_expr(expression),
+            Stmt::Function { name, params, body, .. } => {
+                self.declare(name);
+                self.define(name);
+
+                self.resolve_function(params, body, FunctionType::Function);
+            },
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(then_branch);
+
+                if let Some(else_branch) = else_branch {
+                    self.resolve_stmt(else_branch);
+                }
+            },
+            Stmt::Print { expression, .. } => self.resolve_expr(expression),
+            Stmt::Return { keyword, value, .. } => {
+                if self.current_function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(keyword, "Can't return a value from an initializer.");
+                    }
+
+                    self.resolve_expr(value);
+                }
+            },
+            Stmt::Var { name, initializer, .. } => {
+                self.declare(name);
+                self.resolve_expr(initializer);
+                self.define(name);
+            },
+            Stmt::While { condition, body, increment, .. } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(body);
+
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment);
+                }
+            },
+            Stmt::Comment { .. } | Stmt::Break { .. } | Stmt::Continue { .. } => {},
+        }
+    }
+}
+
+impl ExprVisitor<()> for Resolver<'_> {
+    fn visit(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign { id, name, value } => {
+                self.resolve_expr(value);
+                self.resolve_local(*id, name);
+            },
+            Expr::Binary { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            },
+            Expr::Call { callee, arguments, .. } => {
+                self.resolve_expr(callee);
+
+                for argument in arguments.iter() {
+                    self.resolve_expr(argument);
+                }
+            },
+            Expr::Get { object, .. } => self.resolve_expr(object),
+            Expr::Grouping { expression, .. } => self.resolve_expr(expression),
+            Expr::Index { object, index, .. } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            },
+            Expr::Interpolation { parts } => {
+                for part in parts.iter() {
+                    self.resolve_expr(part);
+                }
+            },
+            Expr::Lambda { params, body, .. } => self.resolve_function(params, body, FunctionType::Function),
+            Expr::List { elements, .. } => {
+                for element in elements.iter() {
+                    self.resolve_expr(element);
+                }
+            },
+            Expr::Literal { .. } => {},
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries.iter() {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            },
+            Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            },
+            Expr::Set { object, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            },
+            Expr::SetIndex { object, index, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            },
+            Expr::Super { id, keyword, .. } => {
+                match self.current_class {
+                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => self.error(keyword, "Can't use 'super' in a class with no superclass."),
+                    ClassType::Subclass => {},
+                }
+
+                self.resolve_local(*id, keyword);
+            },
+            Expr::This { id, keyword } => {
+                if self.current_class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+
+                self.resolve_local(*id, keyword);
+            },
+            Expr::Unary { right, .. } => self.resolve_expr(right),
+            Expr::Variable { id, name } => {
+                if let Some(scope) = self.scopes.last() {
+                    if scope.get(&name.lexeme) == Some(&false) {
+                        self.error(name, "Can't read local variable in its own initializer.");
+                    }
+                }
+
+                self.resolve_local(*id, name);
+            },
+        }
+    }
+}
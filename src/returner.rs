@@ -1,22 +1,18 @@
-use crate::environment::{BObject, Object};
+use crate::{environment::BObject, error::RuntimeError};
 
-pub struct Return{
+/// Non-local exits that unwind the interpreter through enclosing statements.
+#[derive(Debug)]
+pub enum Unwind {
+    Return(BObject),
+    Break,
+    Continue,
+    Error(RuntimeError),
 }
 
-static mut RETURN: Option<BObject> = None;
-
-impl Return{
-    pub fn get() -> BObject {
-        match unsafe { RETURN.to_owned() } {
-            Some(v) => {
-                Return::add(Box::new(Object::Nil));
-                v
-            },
-            None => Box::new(Object::Nil),
-        }
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
     }
+}
 
-    pub fn add(value: BObject) {
-        unsafe { RETURN = Some(value) }
-    }
-}
\ No newline at end of file
+pub type ExecResult = Result<(), Unwind>;
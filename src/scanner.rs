@@ -1,4 +1,4 @@
-use crate::{char_extensions::CharExtensions, environment::Object, error::ErrorHandler, string_extensions::StringExtensions, token::{Token, TokenType}};
+use crate::{char_extensions::CharExtensions, environment::Object, error::Diagnostic, token::{Comment, Span, Token, TokenType}};
 
 use lazy_static::lazy_static;
 use std::collections::HashMap;
@@ -6,32 +6,44 @@ use std::collections::HashMap;
 lazy_static! {
     static ref KEYWORDS: HashMap<&'static str, TokenType> = {
         let mut m: HashMap<&'static str, TokenType> = HashMap::new();
-        m.insert("and",    TokenType::And);
-        m.insert("class",  TokenType::Class);
-        m.insert("else",   TokenType::Else);
-        m.insert("false",  TokenType::False);
-        m.insert("for",    TokenType::For);
-        m.insert("fun",    TokenType::Fun);
-        m.insert("if",     TokenType::If);
-        m.insert("nil",    TokenType::Nil);
-        m.insert("or",     TokenType::Or);
-        m.insert("print",  TokenType::Print);
-        m.insert("return", TokenType::Return);
-        m.insert("super",  TokenType::Super);
-        m.insert("this",   TokenType::This);
-        m.insert("true",   TokenType::True);
-        m.insert("var",    TokenType::Var);
-        m.insert("while",  TokenType::While);
+        m.insert("and",      TokenType::And);
+        m.insert("break",    TokenType::Break);
+        m.insert("class",    TokenType::Class);
+        m.insert("continue", TokenType::Continue);
+        m.insert("else",     TokenType::Else);
+        m.insert("false",    TokenType::False);
+        m.insert("for",      TokenType::For);
+        m.insert("fun",      TokenType::Fun);
+        m.insert("if",       TokenType::If);
+        m.insert("nil",      TokenType::Nil);
+        m.insert("or",       TokenType::Or);
+        m.insert("print",    TokenType::Print);
+        m.insert("return",   TokenType::Return);
+        m.insert("super",    TokenType::Super);
+        m.insert("this",     TokenType::This);
+        m.insert("true",     TokenType::True);
+        m.insert("var",      TokenType::Var);
+        m.insert("while",    TokenType::While);
         m
     };
 }
 
 pub struct Scanner{
     source: String,
+    /// Byte offset where the current lexeme starts.
     start: usize,
+    /// Byte offset of the next character to scan.
     current: usize,
     line: usize,
+    /// One-based column of the next character to scan, counted in characters.
+    column: usize,
+    start_line: usize,
+    start_column: usize,
+    pending_comments: Vec<Comment>,
+    /// Unclosed braces inside each `${` of the strings being interpolated.
+    interpolations: Vec<usize>,
     pub tokens: Vec<Token>,
+    pub errors: Vec<Diagnostic>,
 }
 
 impl Scanner{
@@ -40,8 +52,14 @@ impl Scanner{
             source,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
             start: 0,
+            pending_comments: Vec::new(),
+            interpolations: Vec::new(),
             tokens: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
@@ -49,29 +67,27 @@ impl Scanner{
         self.current >= self.source.len()
     }
 
+    /// Characters not yet scanned. Slicing at `current` is constant time
+    /// because it always sits on a character boundary.
+    fn rest(&self) -> std::str::Chars<'_> {
+        self.source[self.current..].chars()
+    }
+
     fn advance(&mut self) -> char {
-        let c = self.source.char_at(self.current);
+        let c = self.peek();
 
-        self.current += 1;
+        self.current += c.len_utf8();
+        self.column += 1;
 
         c
     }
 
-    fn get_current_char(&self) -> char {
-        self.source.char_at(self.current)
-    }
-
     fn peek(&self) -> char {
-        if self.is_end() {return '\0';}
-        self.get_current_char()
+        self.rest().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-
-        self.source.char_at(self.current + 1)
+        self.rest().nth(1).unwrap_or('\0')
     }
 
     fn check_next(&mut self, expected: char) -> bool{
@@ -79,16 +95,41 @@ impl Scanner{
             return false;
         }
 
-        if self.get_current_char() != expected{
+        if self.peek() != expected{
             return false;
         }
 
-        self.current+=1;
+        self.advance();
         true
     }
     
     fn get_value(&self) -> String {
-        self.source.substring(self.start, self.current)
+        self.source[self.start..self.current].to_owned()
+    }
+
+    /// Span of the lexeme scanned so far.
+    fn span(&self) -> Span {
+        Span {
+            offset: self.start,
+            length: self.current - self.start,
+            line: self.start_line,
+            column: self.start_column
+        }
+    }
+
+    fn error(&mut self, span: Span, message: String) {
+        self.errors.push(Diagnostic::at(span, message));
+    }
+
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column;
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.column = 1;
     }
 
     pub fn add_token(&mut self, token_type: TokenType) {
@@ -98,16 +139,32 @@ impl Scanner{
     fn add_token_with_value(&mut self, token_type: TokenType, literal: Box<Object>) {
         let text: String = self.get_value();
 
-        self.tokens.push(Token { token_type, lexeme: text, literal, line: self.line})
+        let comments = std::mem::take(&mut self.pending_comments);
+        let span = self.span();
+
+        self.tokens.push(Token {
+            token_type,
+            lexeme: text,
+            literal,
+            line: span.line,
+            offset: span.offset,
+            column: span.column,
+            length: span.length,
+            comments
+        })
     }
 
     pub fn scan_tokens(&mut self) {
         while !self.is_end() {
-            self.start = self.current;
+            self.begin_token();
             self.scan_token();
         }
-        
-        self.tokens.push(Token::eof(self.line));
+
+        self.begin_token();
+
+        let mut eof = Token::eof(self.span());
+        eof.comments = std::mem::take(&mut self.pending_comments);
+        self.tokens.push(eof);
     }
 
     fn scan_token(&mut self) {
@@ -116,8 +173,29 @@ impl Scanner{
         match c {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
-            '{' => self.add_token(TokenType::LeftBrace),
-            '}' => self.add_token(TokenType::RightBrace),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace)
+            },
+            '}' => {
+                match self.interpolations.last_mut() {
+                    // Closes the embedded expression, the string continues.
+                    Some(0) => {
+                        self.interpolations.pop();
+                        self.string(true);
+                    },
+                    Some(depth) => {
+                        *depth -= 1;
+                        self.add_token(TokenType::RightBrace);
+                    },
+                    None => self.add_token(TokenType::RightBrace),
+                }
+            },
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
+            ':' => self.add_token(TokenType::Colon),
             ',' => self.add_token(TokenType::Comma),
             '.' => self.add_token(TokenType::Dot),
             '-' => self.add_token(TokenType::Minus),
@@ -129,8 +207,14 @@ impl Scanner{
                 self.add_token(if check_next {TokenType::BangEqual} else {TokenType::Bang})
             }
             '=' => {
-                let check_next = self.check_next('=');
-                self.add_token(if check_next {TokenType::EqualEqual} else {TokenType::Equal})
+                let token_type = if self.check_next('=') {
+                    TokenType::EqualEqual
+                } else if self.check_next('>') {
+                    TokenType::Arrow
+                } else {
+                    TokenType::Equal
+                };
+                self.add_token(token_type)
             }
             '<' => {
                 let check_next = self.check_next('=');
@@ -142,9 +226,17 @@ impl Scanner{
             }
             '/' =>{
                 if self.check_next('/'){
+                    // `///` starts a doc comment, but `////` is an ordinary one.
+                    let doc = self.peek() == '/' && self.peek_next() != '/';
+
                     while self.peek() != '\n' && !self.is_end() {
                         _ = self.advance();
                     }
+
+                    let text = self.get_value().trim_end().to_owned();
+                    self.pending_comments.push(Comment { text, span: self.span(), doc });
+                } else if self.check_next('*') {
+                    self.block_comment();
                 } else{
                     self.add_token(TokenType::Slash);
                 }
@@ -152,10 +244,10 @@ impl Scanner{
             ' ' => {}
             '\r' => {}
             '\t' => {}
-            '\n' => {self.line+=1}
-            '"' => {self.string()},
+            '\n' => self.new_line(),
+            '"' => {self.string(false)},
             any => {
-                if any.is_digit(10){
+                if any.is_ascii_digit(){
                     self.number();
                 }
                 else if any.is_alpha(){
@@ -163,13 +255,40 @@ impl Scanner{
                 }
                 else{
                     let message = format!("Unexpected character: {}", any);
-                    ErrorHandler::error(self.line, message);
+                    self.error(self.span(), message);
                 }
             }
         }
     }
 
 
+    /// Skips a `/* ... */` comment, which may contain nested block comments.
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_end() {
+                self.error(self.span(), String::from("Unterminated block comment."));
+                return;
+            }
+
+            match self.advance() {
+                '/' if self.peek() == '*' => {
+                    self.advance();
+                    depth += 1;
+                },
+                '*' if self.peek() == '/' => {
+                    self.advance();
+                    depth -= 1;
+                },
+                '\n' => self.new_line(),
+                _ => {}
+            }
+        }
+
+        self.pending_comments.push(Comment { text: self.get_value(), span: self.span(), doc: false });
+    }
+
     fn identifier(&mut self){
         while self.peek().is_alpha_numeric() {
             self.advance();
@@ -186,40 +305,235 @@ impl Scanner{
         }        
     }
 
+    /// Scans a decimal number with optional fraction and exponent, or a
+    /// `0x`, `0b` or `0o` integer. Digits may be separated by underscores.
     fn number(&mut self){
-        while self.peek().is_digit(10) {
+        let first = self.source[self.start..].starts_with('0');
+        let radix = match self.peek() {
+            'x' | 'X' if first => Some((16, "hexadecimal")),
+            'b' | 'B' if first => Some((2, "binary")),
+            'o' | 'O' if first => Some((8, "octal")),
+            _ => None,
+        };
+
+        let value = match radix {
+            Some((radix, name)) => {
+                self.advance();
+                self.radix_number(radix, name)
+            },
+            None => self.decimal_number(),
+        };
+
+        // A prefixed number running straight into letters or digits, like
+        // `0b102` or `0xfg`, is one malformed literal. A decimal one such as
+        // `123abc` stays a number followed by an identifier.
+        if radix.is_some() && self.peek().is_alpha_numeric() {
+            while self.peek().is_alpha_numeric() {
+                self.advance();
+            }
+
+            if value.is_some() {
+                let message = format!("Invalid number literal '{}'.", self.get_value());
+                self.error(self.span(), message);
+            }
+        }
+
+        self.add_token_with_value(TokenType::Number, Box::new(Object::Number(value.unwrap_or_default())));
+    }
+
+    fn decimal_number(&mut self) -> Option<f64> {
+        // The leading digit was consumed by `scan_token`.
+        let mut valid = self.digits(10, true);
+
+        if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             self.advance();
+            valid &= self.digits(10, false);
         }
 
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        let exponent = match self.peek_next() {
+            '+' | '-' => self.rest().nth(2).is_some_and(|c| c.is_ascii_digit()),
+            c => c.is_ascii_digit(),
+        };
+
+        if matches!(self.peek(), 'e' | 'E') && exponent {
             self.advance();
-            while self.peek().is_digit(10) {
+            if matches!(self.peek(), '+' | '-') {
                 self.advance();
             }
+            valid &= self.digits(10, false);
         }
 
-        let value = self.get_value();
-        let literal = Box::new(Object::Number(value.parse().unwrap_or_default()));
+        if !valid {
+            self.separator_error();
+            return None;
+        }
 
-        self.add_token_with_value(TokenType::Number, literal);
+        self.get_value().replace('_', "").parse().ok()
     }
 
-    fn string(&mut self){
+    fn radix_number(&mut self, radix: u32, name: &str) -> Option<f64> {
+        let digits_start = self.current;
+
+        if !self.peek().is_digit(radix) {
+            let message = format!("Expect {} digits after '{}'.", name, self.get_value());
+            self.error(self.span(), message);
+            return None;
+        }
+
+        if !self.digits(radix, false) {
+            self.separator_error();
+            return None;
+        }
+
+        // Folding into a float keeps literals wider than 64 bits approximate
+        // instead of overflowing.
+        let value = self.source[digits_start..self.current].chars()
+            .filter_map(|c| c.to_digit(radix))
+            .fold(0.0, |value, digit| value * radix as f64 + digit as f64);
+
+        Some(value)
+    }
+
+    /// Consumes digits of `radix` with `_` separators. Returns false when a
+    /// separator is not placed between two digits.
+    fn digits(&mut self, radix: u32, after_digit: bool) -> bool {
+        let mut valid = true;
+        let mut previous_digit = after_digit;
+
+        loop {
+            let c = self.peek();
+
+            if c == '_' {
+                self.advance();
+                valid &= previous_digit && self.peek().is_digit(radix);
+                previous_digit = false;
+            } else if c.is_digit(radix) {
+                self.advance();
+                previous_digit = true;
+            } else {
+                return valid;
+            }
+        }
+    }
+
+    fn separator_error(&mut self) {
+        let message = format!("Invalid digit separator in number literal '{}'.", self.get_value());
+        self.error(self.span(), message);
+    }
+
+    /// Scans string text up to the closing quote, or up to a `${` which
+    /// ends the segment. `continued` is set when resuming after the `}` of an
+    /// embedded expression.
+    fn string(&mut self, continued: bool){
+        let mut value = String::new();
+
         while self.peek() != '"' && !self.is_end() {
-            if self.peek() == '\n'{
-                self.line+=1;
+            if self.peek() == '$' && self.peek_next() == '{' {
+                self.advance();
+                self.advance();
+                self.interpolations.push(0);
+
+                let token_type = if continued { TokenType::InterpolationMid } else { TokenType::Interpolation };
+                self.add_token_with_value(token_type, Box::new(Object::String(value)));
+                return;
+            }
+
+            match self.advance() {
+                '\n' => {
+                    self.new_line();
+                    value.push('\n');
+                },
+                '\\' => {
+                    // Malformed escapes are reported and left out of the value.
+                    if let Some(c) = self.escape() {
+                        value.push(c);
+                    }
+                },
+                c => value.push(c),
             }
-            self.advance();
         }
         
         if self.is_end(){
-            ErrorHandler::error(self.line, String::from("Unterminated string."));
+            self.error(self.span(), String::from("Unterminated string."));
             return;
         }
 
         self.advance();
 
-        let value = self.source.substring(self.start + 1, self.current - 1);
-        self.add_token_with_value(TokenType::String, Box::new(Object::String(value)));
+        let token_type = if continued { TokenType::InterpolationEnd } else { TokenType::String };
+        self.add_token_with_value(token_type, Box::new(Object::String(value)));
     }
-}
\ No newline at end of file
+
+    /// Decodes the escape sequence following a backslash.
+    fn escape(&mut self) -> Option<char> {
+        if self.is_end() {
+            return None;
+        }
+
+        let span = Span {
+            offset: self.current - 1,
+            length: 0,
+            line: self.line,
+            column: self.column - 1
+        };
+
+        let escaped = match self.advance() {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '"' => Some('"'),
+            '\\' => Some('\\'),
+            '0' => Some('\0'),
+            '$' => Some('$'),
+            'u' => return self.unicode_escape(span),
+            _ => None,
+        };
+
+        if escaped.is_none() {
+            let sequence = &self.source[span.offset..self.current];
+            let message = format!("Invalid escape sequence '{}'.", sequence);
+            self.error(Span { length: self.current - span.offset, ..span }, message);
+        }
+
+        escaped
+    }
+
+    /// Decodes the `{XXXX}` part of a `\u{XXXX}` escape of 1 to 6 hex digits.
+    fn unicode_escape(&mut self, span: Span) -> Option<char> {
+        let mut digits = String::new();
+        let mut closed = false;
+
+        if self.peek() == '{' {
+            self.advance();
+
+            while !self.is_end() && self.peek() != '"' && self.peek() != '\n' {
+                let c = self.advance();
+
+                if c == '}' {
+                    closed = true;
+                    break;
+                }
+
+                digits.push(c);
+            }
+        }
+
+        let valid = closed
+            && (1..=6).contains(&digits.len())
+            && digits.chars().all(|c| c.is_ascii_hexdigit());
+
+        let decoded = if valid {
+            u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32)
+        } else {
+            None
+        };
+
+        if decoded.is_none() {
+            let sequence = &self.source[span.offset..self.current];
+            let message = format!("Invalid unicode escape '{}'.", sequence);
+            self.error(Span { length: self.current - span.offset, ..span }, message);
+        }
+
+        decoded
+    }
+}
@@ -1,63 +1,120 @@
 use std::fmt::Display;
 
-use crate::{error::ParserError, expression::Expr, token::Token};
+use crate::{error::ParseError, expression::Expr, token::{Span, Token}};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Stmt{
     Block{
-        statements: Box<[Box<Stmt>]>
+        statements: Box<[Box<Stmt>]>,
+        span: Span
+    },
+    Break{
+        keyword: Token,
+        span: Span
     },
     Class{
         name: Token,
-        superclass: Box<Expr>,
-        methods: Box<[Box<Stmt>]>
+        superclass: Option<Box<Expr>>,
+        methods: Box<[Box<Stmt>]>,
+        /// Text of the `///` comments in front of the declaration.
+        doc: Option<String>,
+        span: Span
+    },
+    /// Source comment, only produced when the parser keeps comments.
+    Comment{
+        text: String,
+        trailing: bool,
+        span: Span
+    },
+    Continue{
+        keyword: Token,
+        span: Span
     },
     Expression{
-        expression: Box<Expr>
+        expression: Box<Expr>,
+        span: Span
     },
     Function{
         name: Token,
         params: Box<[Token]>,
-        body: Box<[Box<Stmt>]>
+        body: Box<[Box<Stmt>]>,
+        /// Text of the `///` comments in front of the declaration.
+        doc: Option<String>,
+        span: Span
     },
     If{
         condition: Box<Expr>,
         then_branch: Box<Stmt>,
-        else_branch: Option<Box<Stmt>>
+        else_branch: Option<Box<Stmt>>,
+        span: Span
     },
     Print{
-        expression: Box<Expr>
+        expression: Box<Expr>,
+        span: Span
     },
     Return{
         keyword: Token,
-        value: Option<Box<Expr>>
+        value: Option<Box<Expr>>,
+        span: Span
     },
     Var{
         name: Token,
-        initializer: Box<Expr>
+        initializer: Box<Expr>,
+        span: Span
     },
     While{
         condition: Box<Expr>,
-        body: Box<Stmt>
+        body: Box<Stmt>,
+        /// Increment clause of a desugared `for` loop, run after each iteration.
+        increment: Option<Box<Expr>>,
+        syntax: LoopSyntax,
+        span: Span
     },
 }
 
+/// How a loop was written, so the formatter can print it back unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoopSyntax {
+    While,
+    /// A `for` loop, with which of its optional clauses were present. With
+    /// an initializer, the loop is the last statement of a block holding it.
+    For { initializer: bool, condition: bool },
+}
+
 impl Stmt {
-    pub fn wrap(self) -> Result<Box<Stmt>, ParserError>{
+    /// Source range covered by the statement.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block { span, .. } |
+            Stmt::Break { span, .. } |
+            Stmt::Class { span, .. } |
+            Stmt::Comment { span, .. } |
+            Stmt::Continue { span, .. } |
+            Stmt::Expression { span, .. } |
+            Stmt::Function { span, .. } |
+            Stmt::If { span, .. } |
+            Stmt::Print { span, .. } |
+            Stmt::Return { span, .. } |
+            Stmt::Var { span, .. } |
+            Stmt::While { span, .. } => *span,
+        }
+    }
+
+    pub fn wrap(self) -> Result<Box<Stmt>, ParseError>{
         Ok(Box::new(self))
     }
 
-    pub fn accept<R>(&self, visitor: &mut impl StmtVisitor<R>) -> () {
+    pub fn accept<R>(&self, visitor: &mut impl StmtVisitor<R>) -> R {
         visitor.visit(self)
     }
 }
 
 impl Display for Stmt {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(f, "{}", self)
+        write!(f, "{:?}", self)
     }
 }
 
 pub trait StmtVisitor<R> {
-    fn visit(&mut self, stmt: &Stmt) -> ();
+    fn visit(&mut self, stmt: &Stmt) -> R;
 }
@@ -5,20 +5,22 @@ use crate::environment::Object;
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
-  LeftParen, RightParen, LeftBrace, RightBrace,
-  Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
+  LeftParen, RightParen, LeftBrace, RightBrace, LeftBracket, RightBracket,
+  Colon, Comma, Dot, Minus, Plus, Semicolon, Slash, Star,
 
   // One or two character tokens.
   Bang, BangEqual,
-  Equal, EqualEqual,
+  Equal, EqualEqual, Arrow,
   Greater, GreaterEqual,
   Less, LessEqual,
 
   // Literals.
   Identifier, String, Number,
+  // Segments of an interpolated string: `"text${`, `}text${` and `}text"`.
+  Interpolation, InterpolationMid, InterpolationEnd,
 
   // Keywords.
-  And, Class, Else, False, Fun, For, If, Nil, Or,
+  And, Break, Class, Continue, Else, False, Fun, For, If, Nil, Or,
   Print, Return, Super, This, True, Var, While,
 
   Eof
@@ -31,6 +33,9 @@ impl TokenType {
             TokenType::RightParen => "RIGHT_PAREN",
             TokenType::LeftBrace => "LEFT_BRACE",
             TokenType::RightBrace => "RIGHT_BRACE",
+            TokenType::LeftBracket => "LEFT_BRACKET",
+            TokenType::RightBracket => "RIGHT_BRACKET",
+            TokenType::Colon => "COLON",
             TokenType::Comma => "COMMA",
             TokenType::Dot => "DOT",
             TokenType::Minus => "MINUS",
@@ -42,6 +47,7 @@ impl TokenType {
             TokenType::BangEqual => "BANG_EQUAL",
             TokenType::Equal => "EQUAL",
             TokenType::EqualEqual => "EQUAL_EQUAL",
+            TokenType::Arrow => "ARROW",
             TokenType::Greater => "GREATER",
             TokenType::GreaterEqual => "GREATER_EQUAL",
             TokenType::Less => "LESS",
@@ -49,8 +55,13 @@ impl TokenType {
             TokenType::Identifier => "IDENTIFIER",
             TokenType::String => "STRING",
             TokenType::Number => "NUMBER",
+            TokenType::Interpolation => "INTERPOLATION",
+            TokenType::InterpolationMid => "INTERPOLATION_MID",
+            TokenType::InterpolationEnd => "INTERPOLATION_END",
             TokenType::And => "AND",
+            TokenType::Break => "BREAK",
             TokenType::Class => "CLASS",
+            TokenType::Continue => "CONTINUE",
             TokenType::Else => "ELSE",
             TokenType::False => "FALSE",
             TokenType::Fun => "FUN",
@@ -76,36 +87,88 @@ impl Display for TokenType{
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// A range of source text together with the position where it starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub offset: usize,
+    pub length: usize,
+    pub line: usize,
+    pub column: usize
+}
+
+impl Span {
+    /// Span from the start of `self` to the end of `end`.
+    pub fn to(self, end: Span) -> Span {
+        Span {
+            length: (end.offset + end.length).saturating_sub(self.offset),
+            ..self
+        }
+    }
+}
+
+/// A comment kept as trivia on the token that follows it.
+#[derive(Clone, Debug)]
+pub struct Comment {
+    pub text: String,
+    pub span: Span,
+    /// Set for `///` comments, which the parser attaches to declarations.
+    pub doc: bool
+}
+
+#[derive(Clone, Debug)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Box<Object>,
-    pub line: usize
+    pub line: usize,
+    /// Byte offset of the lexeme in the source.
+    pub offset: usize,
+    /// One-based column of the first character of the lexeme.
+    pub column: usize,
+    /// Length of the lexeme in bytes.
+    pub length: usize,
+    pub comments: Vec<Comment>
 }
 
 impl Token {
     pub fn with_lexeme(lexeme: String, token_type: TokenType) -> Token {
         Token {
             line: 0,
+            offset: 0,
+            column: 0,
+            length: lexeme.len(),
             lexeme,
             token_type,
-            literal: Box::new(Object::Nil)
+            literal: Box::new(Object::Nil),
+            comments: Vec::new()
         }
     }
 
-    pub fn eof(line: usize) -> Token {
+    pub fn eof(span: Span) -> Token {
         Token{
             token_type: TokenType::Eof,
             lexeme: String::new(),
-            line,
-            literal: Box::new(Object::Nil)
+            line: span.line,
+            offset: span.offset,
+            column: span.column,
+            length: 0,
+            literal: Box::new(Object::Nil),
+            comments: Vec::new()
         }
     }
+
+    pub fn span(&self) -> Span {
+        Span { offset: self.offset, length: self.length, line: self.line, column: self.column }
+    }
 }
 
 impl Display for Token{
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {} {}", self.token_type, self.lexeme, self.literal.to_string())
+        match self.literal.as_ref() {
+            // Numbers are shown in normalized decimal form, integers with a
+            // trailing `.0`, whatever notation the source used.
+            Object::Number(n) if n.fract() == 0.0 => write!(f, "{} {} {:.1}", self.token_type, self.lexeme, n),
+            _ => write!(f, "{} {} {}", self.token_type, self.lexeme, self.literal),
+        }
     }
 }
\ No newline at end of file
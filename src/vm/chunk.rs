@@ -0,0 +1,139 @@
+use crate::{environment::Object, token::Span};
+
+/// Instructions of the bytecode VM. Operands follow the opcode byte, in
+/// the order given for each instruction; `u16` operands are big-endian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum OpCode {
+    /// `u16` constant index.
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    /// `u8` stack slot.
+    GetLocal,
+    /// `u8` stack slot.
+    SetLocal,
+    /// `u16` name constant.
+    GetGlobal,
+    /// `u16` name constant.
+    DefineGlobal,
+    /// `u16` name constant.
+    SetGlobal,
+    /// `u8` upvalue index.
+    GetUpvalue,
+    /// `u8` upvalue index.
+    SetUpvalue,
+    /// `u16` name constant. Fails if the value on top of the stack is a
+    /// variable that was declared without a value and never assigned.
+    CheckInitialized,
+    /// `u16` name constant.
+    GetProperty,
+    /// `u16` name constant.
+    SetProperty,
+    /// `u16` name constant.
+    GetSuper,
+    GetIndex,
+    SetIndex,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    /// `u16` forward offset.
+    Jump,
+    /// `u16` forward offset. Leaves the condition on the stack.
+    JumpIfFalse,
+    /// `u16` backward offset.
+    Loop,
+    /// `u8` argument count.
+    Call,
+    /// `u16` function constant, then an `is_local` byte and an index byte
+    /// for each upvalue the function captures.
+    Closure,
+    CloseUpvalue,
+    Return,
+    /// `u16` name constant, `u8` method count and `u8` flag set when the
+    /// class has a superclass, which sits below the methods on the stack.
+    Class,
+    /// `u8` number of parts to join into a string.
+    Interpolate,
+    /// `u16` element count.
+    List,
+    /// `u16` entry count.
+    Map,
+}
+
+impl OpCode {
+    const ALL: [OpCode; 42] = [
+        OpCode::Constant, OpCode::Nil, OpCode::True, OpCode::False, OpCode::Pop,
+        OpCode::GetLocal, OpCode::SetLocal, OpCode::GetGlobal, OpCode::DefineGlobal, OpCode::SetGlobal,
+        OpCode::GetUpvalue, OpCode::SetUpvalue, OpCode::CheckInitialized,
+        OpCode::GetProperty, OpCode::SetProperty, OpCode::GetSuper, OpCode::GetIndex, OpCode::SetIndex,
+        OpCode::Equal, OpCode::NotEqual, OpCode::Greater, OpCode::GreaterEqual, OpCode::Less, OpCode::LessEqual,
+        OpCode::Add, OpCode::Subtract, OpCode::Multiply, OpCode::Divide, OpCode::Not, OpCode::Negate,
+        OpCode::Print, OpCode::Jump, OpCode::JumpIfFalse, OpCode::Loop, OpCode::Call, OpCode::Closure,
+        OpCode::CloseUpvalue, OpCode::Return, OpCode::Class, OpCode::Interpolate, OpCode::List, OpCode::Map,
+    ];
+
+    /// Decodes an opcode byte, or `None` for a byte that isn't an opcode.
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        Self::ALL.get(byte as usize).copied().filter(|op| *op as u8 == byte)
+    }
+}
+
+/// Compiled bytecode of one function.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Object>,
+    /// Source span of the instructions starting at each offset, recorded
+    /// only where the span changes.
+    spans: Vec<(usize, Span)>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, byte: u8, span: Span) {
+        if self.spans.last().map(|(_, last)| *last) != Some(span) {
+            self.spans.push((self.code.len(), span));
+        }
+
+        self.code.push(byte);
+    }
+
+    pub fn add_constant(&mut self, value: Object) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    pub fn read_short(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    /// Source span of the instruction at `offset`.
+    pub fn span_at(&self, offset: usize) -> Span {
+        let index = self.spans.partition_point(|(start, _)| *start <= offset);
+
+        match index {
+            0 => Span::default(),
+            _ => self.spans[index - 1].1,
+        }
+    }
+
+    pub fn line_at(&self, offset: usize) -> usize {
+        self.span_at(offset).line
+    }
+}
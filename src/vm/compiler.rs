@@ -0,0 +1,724 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{environment::Object, error::Diagnostic, expression::{Expr, ExprVisitor}, statement::{Stmt, StmtVisitor}, token::{Span, Token, TokenType}};
+
+use super::{chunk::{Chunk, OpCode}, function::{Closure, Function}};
+
+const MAX_LOCALS: usize = u8::MAX as usize + 1;
+const MAX_UPVALUES: usize = u8::MAX as usize + 1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    captured: bool,
+    /// Declared without a value, so reads check that it was assigned since.
+    unassigned: bool,
+}
+
+struct Upvalue {
+    index: u8,
+    is_local: bool,
+}
+
+struct Loop {
+    /// Scope depth outside the loop body.
+    depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// Constant value shared by every instruction of a chunk that uses it.
+#[derive(PartialEq, Eq, Hash)]
+enum ConstantKey {
+    String(String),
+    Number(u64),
+}
+
+impl ConstantKey {
+    fn new(value: &Object) -> Option<Self> {
+        match value {
+            Object::String(s) => Some(ConstantKey::String(s.to_owned())),
+            Object::Number(n) => Some(ConstantKey::Number(n.to_bits())),
+            _ => None,
+        }
+    }
+}
+
+/// Compilation state of a function whose body is being compiled.
+struct FunctionState {
+    function: Function,
+    kind: FunctionKind,
+    locals: Vec<Local>,
+    upvalues: Vec<Upvalue>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    /// Indices of the names and literals already in the chunk.
+    constants: HashMap<ConstantKey, u16>,
+    /// Set once the chunk ran out of constants, so that is reported once.
+    constants_full: bool,
+}
+
+/// Where a variable lives, as found by [`Compiler::resolve`].
+enum Slot {
+    Local(u8, bool),
+    Upvalue(u8, bool),
+    Global(u16),
+}
+
+/// Compiles a resolved program into bytecode for [`super::Vm`].
+pub struct Compiler {
+    states: Vec<FunctionState>,
+    errors: Vec<Diagnostic>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self { states: Vec::new(), errors: Vec::new() }
+    }
+
+    /// Compiles a program into the function that runs it as a script, or
+    /// returns the errors that prevented it.
+    pub fn compile(mut self, statements: &[Box<Stmt>]) -> Result<Rc<Function>, Vec<Diagnostic>> {
+        self.begin_function(FunctionKind::Script, String::new(), 0);
+
+        for stmt in statements.iter() {
+            self.statement(stmt);
+        }
+
+        let span = statements.last().map(|stmt| stmt.span()).unwrap_or_default();
+        let (function, _) = self.end_function(span);
+
+        if self.errors.is_empty() {
+            Ok(Rc::new(function))
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        stmt.accept(self)
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        expr.accept(self)
+    }
+
+    fn state(&self) -> &FunctionState {
+        self.states.last().expect("No function is being compiled.")
+    }
+
+    fn state_mut(&mut self) -> &mut FunctionState {
+        self.states.last_mut().expect("No function is being compiled.")
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state_mut().function.chunk
+    }
+
+    fn error(&mut self, span: Span, message: &str) {
+        self.errors.push(Diagnostic::at(span, message.to_owned()));
+    }
+
+    fn emit(&mut self, op: OpCode, span: Span) {
+        self.chunk().write(op as u8, span);
+    }
+
+    fn emit_byte(&mut self, op: OpCode, operand: u8, span: Span) {
+        self.emit(op, span);
+        self.chunk().write(operand, span);
+    }
+
+    fn emit_short(&mut self, op: OpCode, operand: u16, span: Span) {
+        self.emit(op, span);
+
+        for byte in operand.to_be_bytes() {
+            self.chunk().write(byte, span);
+        }
+    }
+
+    /// Emits a jump with a placeholder offset and returns where to patch it.
+    fn emit_jump(&mut self, op: OpCode, span: Span) -> usize {
+        self.emit_short(op, u16::MAX, span);
+        self.chunk().code.len() - 2
+    }
+
+    /// Points the jump operand at `offset` to the next instruction.
+    fn patch_jump(&mut self, offset: usize, span: Span) {
+        let distance = self.chunk().code.len() - offset - 2;
+
+        let Ok(distance) = u16::try_from(distance) else {
+            return self.error(span, "Too much code to jump over.");
+        };
+
+        let [high, low] = distance.to_be_bytes();
+        self.chunk().code[offset] = high;
+        self.chunk().code[offset + 1] = low;
+    }
+
+    fn emit_loop(&mut self, start: usize, span: Span) {
+        let distance = self.chunk().code.len() - start + 3;
+
+        match u16::try_from(distance) {
+            Ok(distance) => self.emit_short(OpCode::Loop, distance, span),
+            Err(_) => self.error(span, "Loop body too large."),
+        }
+    }
+
+    fn make_constant(&mut self, value: Object, span: Span) -> u16 {
+        let key = ConstantKey::new(&value);
+
+        if let Some(index) = key.as_ref().and_then(|key| self.state().constants.get(key)) {
+            return *index;
+        }
+
+        let Ok(index) = u16::try_from(self.chunk().constants.len()) else {
+            if !self.state().constants_full {
+                self.state_mut().constants_full = true;
+                self.error(span, "Too many constants in one chunk.");
+            }
+            return 0;
+        };
+
+        self.chunk().add_constant(value);
+
+        if let Some(key) = key {
+            self.state_mut().constants.insert(key, index);
+        }
+
+        index
+    }
+
+    fn identifier_constant(&mut self, name: &str, span: Span) -> u16 {
+        self.make_constant(Object::String(name.to_owned()), span)
+    }
+
+    fn begin_function(&mut self, kind: FunctionKind, name: String, arity: usize) {
+        // Slot zero holds the called closure, or the receiver in methods.
+        let receiver = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => "this",
+            FunctionKind::Script | FunctionKind::Function => "",
+        };
+
+        self.states.push(FunctionState {
+            function: Function { name, arity, ..Function::default() },
+            kind,
+            locals: vec![Local { name: receiver.to_owned(), depth: 0, captured: false, unassigned: false }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+            constants: HashMap::new(),
+            constants_full: false,
+        });
+    }
+
+    fn emit_return(&mut self, span: Span) {
+        if self.state().kind == FunctionKind::Initializer {
+            self.emit_byte(OpCode::GetLocal, 0, span);
+        } else {
+            self.emit(OpCode::Nil, span);
+        }
+
+        self.emit(OpCode::Return, span);
+    }
+
+    fn end_function(&mut self, span: Span) -> (Function, Vec<Upvalue>) {
+        self.emit_return(span);
+
+        let mut state = self.states.pop().expect("No function is being compiled.");
+        state.function.upvalue_count = state.upvalues.len();
+
+        (state.function, state.upvalues)
+    }
+
+    /// Compiles a function body and emits the closure that creates it.
+    fn function(&mut self, kind: FunctionKind, name: &str, params: &[Token], body: &[Box<Stmt>], span: Span) {
+        self.begin_function(kind, name.to_owned(), params.len());
+        self.state_mut().scope_depth += 1;
+
+        for param in params.iter() {
+            self.add_local(param, false);
+        }
+
+        for stmt in body.iter() {
+            self.statement(stmt);
+        }
+
+        let (function, upvalues) = self.end_function(span);
+        let template = Object::Closure(Rc::new(Closure::new(Rc::new(function))));
+        let constant = self.make_constant(template, span);
+        self.emit_short(OpCode::Closure, constant, span);
+
+        for upvalue in upvalues.iter() {
+            self.chunk().write(upvalue.is_local as u8, span);
+            self.chunk().write(upvalue.index, span);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.state_mut().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self, span: Span) {
+        self.state_mut().scope_depth -= 1;
+        let depth = self.state().scope_depth;
+
+        self.discard_locals(depth, span);
+
+        let state = self.state_mut();
+        while state.locals.last().is_some_and(|local| local.depth > depth) {
+            state.locals.pop();
+        }
+    }
+
+    /// Emits the instructions removing the locals deeper than `depth` from
+    /// the stack. The compiler keeps them, so jumps out of scopes can use it.
+    fn discard_locals(&mut self, depth: usize, span: Span) {
+        let captured = self.state().locals.iter().rev()
+            .take_while(|local| local.depth > depth)
+            .map(|local| local.captured)
+            .collect::<Vec<bool>>();
+
+        for captured in captured {
+            self.emit(if captured { OpCode::CloseUpvalue } else { OpCode::Pop }, span);
+        }
+    }
+
+    fn add_local(&mut self, name: &Token, unassigned: bool) {
+        self.add_local_named(&name.lexeme, unassigned, name.span());
+    }
+
+    fn add_local_named(&mut self, name: &str, unassigned: bool, span: Span) {
+        if self.state().locals.len() == MAX_LOCALS {
+            return self.error(span, "Too many local variables in function.");
+        }
+
+        let depth = self.state().scope_depth;
+        self.state_mut().locals.push(Local { name: name.to_owned(), depth, captured: false, unassigned });
+    }
+
+    /// Declares a variable in the current scope. Locals take the next stack
+    /// slot, globals get the constant naming them for `define_variable`.
+    fn declare_variable(&mut self, name: &Token, unassigned: bool) -> Option<u16> {
+        if self.state().scope_depth == 0 {
+            return Some(self.identifier_constant(&name.lexeme, name.span()));
+        }
+
+        self.add_local(name, unassigned);
+        None
+    }
+
+    fn define_variable(&mut self, global: Option<u16>, span: Span) {
+        if let Some(global) = global {
+            self.emit_short(OpCode::DefineGlobal, global, span);
+        }
+    }
+
+    fn resolve_local(&self, state: usize, name: &str) -> Option<(u8, bool)> {
+        self.states[state].locals.iter()
+            .enumerate()
+            .rev()
+            .find(|(_, local)| local.name == name)
+            .map(|(slot, local)| (slot as u8, local.unassigned))
+    }
+
+    fn resolve_upvalue(&mut self, state: usize, name: &str, span: Span) -> Option<(u8, bool)> {
+        if state == 0 {
+            return None;
+        }
+
+        if let Some((slot, unassigned)) = self.resolve_local(state - 1, name) {
+            self.states[state - 1].locals[slot as usize].captured = true;
+            return Some((self.add_upvalue(state, slot, true, span), unassigned));
+        }
+
+        let (index, unassigned) = self.resolve_upvalue(state - 1, name, span)?;
+        Some((self.add_upvalue(state, index, false, span), unassigned))
+    }
+
+    fn add_upvalue(&mut self, state: usize, index: u8, is_local: bool, span: Span) -> u8 {
+        let upvalues = &self.states[state].upvalues;
+
+        if let Some(existing) = upvalues.iter().position(|upvalue| upvalue.index == index && upvalue.is_local == is_local) {
+            return existing as u8;
+        }
+
+        if upvalues.len() == MAX_UPVALUES {
+            self.error(span, "Too many closure variables in function.");
+            return 0;
+        }
+
+        self.states[state].upvalues.push(Upvalue { index, is_local });
+        (self.states[state].upvalues.len() - 1) as u8
+    }
+
+    fn resolve(&mut self, name: &str, span: Span) -> Slot {
+        let state = self.states.len() - 1;
+
+        if let Some((slot, unassigned)) = self.resolve_local(state, name) {
+            return Slot::Local(slot, unassigned);
+        }
+
+        if let Some((index, unassigned)) = self.resolve_upvalue(state, name, span) {
+            return Slot::Upvalue(index, unassigned);
+        }
+
+        Slot::Global(self.identifier_constant(name, span))
+    }
+
+    fn get_variable(&mut self, name: &str, span: Span) {
+        let unassigned = match self.resolve(name, span) {
+            Slot::Local(slot, unassigned) => {
+                self.emit_byte(OpCode::GetLocal, slot, span);
+                unassigned
+            },
+            Slot::Upvalue(index, unassigned) => {
+                self.emit_byte(OpCode::GetUpvalue, index, span);
+                unassigned
+            },
+            // Globals are checked when they are read.
+            Slot::Global(constant) => {
+                self.emit_short(OpCode::GetGlobal, constant, span);
+                false
+            },
+        };
+
+        if unassigned {
+            let constant = self.identifier_constant(name, span);
+            self.emit_short(OpCode::CheckInitialized, constant, span);
+        }
+    }
+
+    fn set_variable(&mut self, name: &str, span: Span) {
+        match self.resolve(name, span) {
+            Slot::Local(slot, _) => self.emit_byte(OpCode::SetLocal, slot, span),
+            Slot::Upvalue(index, _) => self.emit_byte(OpCode::SetUpvalue, index, span),
+            Slot::Global(constant) => self.emit_short(OpCode::SetGlobal, constant, span),
+        }
+    }
+
+    /// Emits the instruction building a value out of `count` stack entries,
+    /// reporting counts that don't fit its operand.
+    fn emit_count(&mut self, op: OpCode, count: usize, limit: usize, span: Span, message: &str) {
+        if count > limit {
+            return self.error(span, message);
+        }
+
+        match op {
+            OpCode::Call | OpCode::Interpolate => self.emit_byte(op, count as u8, span),
+            _ => self.emit_short(op, count as u16, span),
+        }
+    }
+
+    fn class_declaration(&mut self, name: &Token, superclass: &Option<Box<Expr>>, methods: &[Box<Stmt>]) {
+        let constant = self.identifier_constant(&name.lexeme, name.span());
+
+        // A local class gets its slot before the superclass is pushed above it.
+        let global = if self.state().scope_depth == 0 {
+            Some(constant)
+        } else {
+            self.emit(OpCode::Nil, name.span());
+            self.add_local(name, false);
+            None
+        };
+
+        let mut span = name.span();
+
+        if let Some(superclass) = superclass {
+            span = superclass.span();
+            self.expression(superclass);
+            self.begin_scope();
+            self.add_local_named("super", false, span);
+        }
+
+        if methods.len() > u8::MAX as usize {
+            self.error(name.span(), "Too many methods in one class.");
+        }
+
+        for method in methods.iter() {
+            if let Stmt::Function { name, params, body, span, .. } = method.as_ref() {
+                let kind = if name.lexeme == "init" { FunctionKind::Initializer } else { FunctionKind::Method };
+                self.function(kind, &name.lexeme, params, body, *span);
+            }
+        }
+
+        self.emit_short(OpCode::Class, constant, span);
+        self.chunk().write(methods.len() as u8, span);
+        self.chunk().write(superclass.is_some() as u8, span);
+
+        match global {
+            Some(global) => self.define_variable(Some(global), name.span()),
+            None => {
+                self.set_variable(&name.lexeme, name.span());
+                self.emit(OpCode::Pop, name.span());
+            }
+        }
+
+        if superclass.is_some() {
+            self.end_scope(span);
+        }
+    }
+
+    fn while_statement(&mut self, condition: &Expr, body: &Stmt, increment: &Option<Box<Expr>>, span: Span) {
+        let start = self.chunk().code.len();
+
+        self.expression(condition);
+        let exit = self.emit_jump(OpCode::JumpIfFalse, span);
+        self.emit(OpCode::Pop, span);
+
+        let depth = self.state().scope_depth;
+        self.state_mut().loops.push(Loop { depth, breaks: Vec::new(), continues: Vec::new() });
+
+        self.statement(body);
+
+        let jumps = self.state_mut().loops.pop().expect("Loop was pushed above.");
+
+        // `continue` still runs the increment clause of a `for` loop.
+        for jump in jumps.continues {
+            self.patch_jump(jump, span);
+        }
+
+        if let Some(increment) = increment {
+            self.expression(increment);
+            self.emit(OpCode::Pop, span);
+        }
+
+        self.emit_loop(start, span);
+
+        self.patch_jump(exit, span);
+        self.emit(OpCode::Pop, span);
+
+        for jump in jumps.breaks {
+            self.patch_jump(jump, span);
+        }
+    }
+
+    /// Leaves the scopes inside the innermost loop and jumps out of its body.
+    fn loop_jump(&mut self, is_break: bool, span: Span) {
+        let Some(depth) = self.state().loops.last().map(|jumps| jumps.depth) else {
+            return;
+        };
+
+        self.discard_locals(depth, span);
+        let jump = self.emit_jump(OpCode::Jump, span);
+
+        if let Some(jumps) = self.state_mut().loops.last_mut() {
+            if is_break {
+                jumps.breaks.push(jump);
+            } else {
+                jumps.continues.push(jump);
+            }
+        }
+    }
+}
+
+impl StmtVisitor<()> for Compiler {
+    fn visit(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { statements, span } => {
+                self.begin_scope();
+
+                for stmt in statements.iter() {
+                    self.statement(stmt);
+                }
+
+                self.end_scope(*span);
+            },
+            Stmt::Break { span, .. } => self.loop_jump(true, *span),
+            Stmt::Class { name, superclass, methods, .. } => self.class_declaration(name, superclass, methods),
+            Stmt::Comment { .. } => {},
+            Stmt::Continue { span, .. } => self.loop_jump(false, *span),
+            Stmt::Expression { expression, span } => {
+                self.expression(expression);
+                self.emit(OpCode::Pop, *span);
+            },
+            Stmt::Function { name, params, body, span, .. } => {
+                let global = self.declare_variable(name, false);
+                self.function(FunctionKind::Function, &name.lexeme, params, body, *span);
+                self.define_variable(global, name.span());
+            },
+            Stmt::If { condition, then_branch, else_branch, span } => {
+                self.expression(condition);
+
+                let then_jump = self.emit_jump(OpCode::JumpIfFalse, *span);
+                self.emit(OpCode::Pop, *span);
+                self.statement(then_branch);
+
+                let else_jump = self.emit_jump(OpCode::Jump, *span);
+                self.patch_jump(then_jump, *span);
+                self.emit(OpCode::Pop, *span);
+
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+
+                self.patch_jump(else_jump, *span);
+            },
+            Stmt::Print { expression, span } => {
+                self.expression(expression);
+                self.emit(OpCode::Print, *span);
+            },
+            Stmt::Return { value, span, .. } => {
+                match value {
+                    Some(value) if self.state().kind != FunctionKind::Initializer => {
+                        self.expression(value);
+                        self.emit(OpCode::Return, *span);
+                    },
+                    _ => self.emit_return(*span),
+                }
+            },
+            Stmt::Var { name, initializer, .. } => {
+                let unassigned = matches!(initializer.as_ref(), Expr::Literal { value, .. } if matches!(value.as_ref(), Object::Unitialized));
+
+                let global = self.declare_variable(name, unassigned);
+                self.expression(initializer);
+                self.define_variable(global, name.span());
+            },
+            Stmt::While { condition, body, increment, span, .. } => self.while_statement(condition, body, increment, *span),
+        }
+    }
+}
+
+impl ExprVisitor<()> for Compiler {
+    fn visit(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign { name, value, .. } => {
+                self.expression(value);
+                self.set_variable(&name.lexeme, name.span());
+            },
+            Expr::Binary { left, operator, right } => {
+                self.expression(left);
+                self.expression(right);
+
+                let op = match operator.token_type {
+                    TokenType::BangEqual => OpCode::NotEqual,
+                    TokenType::EqualEqual => OpCode::Equal,
+                    TokenType::Greater => OpCode::Greater,
+                    TokenType::GreaterEqual => OpCode::GreaterEqual,
+                    TokenType::Less => OpCode::Less,
+                    TokenType::LessEqual => OpCode::LessEqual,
+                    TokenType::Plus => OpCode::Add,
+                    TokenType::Minus => OpCode::Subtract,
+                    TokenType::Star => OpCode::Multiply,
+                    _ => OpCode::Divide,
+                };
+                self.emit(op, operator.span());
+            },
+            Expr::Call { callee, paren, arguments } => {
+                self.expression(callee);
+
+                for argument in arguments.iter() {
+                    self.expression(argument);
+                }
+
+                self.emit_count(OpCode::Call, arguments.len(), u8::MAX as usize, paren.span(), "Can't have more than 255 arguments.");
+            },
+            Expr::Get { object, name } => {
+                self.expression(object);
+                let constant = self.identifier_constant(&name.lexeme, name.span());
+                self.emit_short(OpCode::GetProperty, constant, name.span());
+            },
+            Expr::Grouping { expression, .. } => self.expression(expression),
+            Expr::Index { object, bracket, index } => {
+                self.expression(object);
+                self.expression(index);
+                self.emit(OpCode::GetIndex, bracket.span());
+            },
+            Expr::Interpolation { parts } => {
+                for part in parts.iter() {
+                    self.expression(part);
+                }
+
+                self.emit_count(OpCode::Interpolate, parts.len(), u8::MAX as usize, expr.span(), "Too many parts in interpolated string.");
+            },
+            Expr::Lambda { params, body, span, .. } => self.function(FunctionKind::Function, "<lambda>", params, body, *span),
+            Expr::List { elements, span } => {
+                for element in elements.iter() {
+                    self.expression(element);
+                }
+
+                self.emit_count(OpCode::List, elements.len(), u16::MAX as usize, *span, "Too many elements in list literal.");
+            },
+            Expr::Literal { value, span, .. } => {
+                match value.as_ref() {
+                    Object::Nil => self.emit(OpCode::Nil, *span),
+                    Object::Boolean(true) => self.emit(OpCode::True, *span),
+                    Object::Boolean(false) => self.emit(OpCode::False, *span),
+                    value => {
+                        let constant = self.make_constant(value.to_owned(), *span);
+                        self.emit_short(OpCode::Constant, constant, *span);
+                    },
+                }
+            },
+            Expr::Logical { left, operator, right } => {
+                self.expression(left);
+
+                if operator.token_type == TokenType::Or {
+                    let else_jump = self.emit_jump(OpCode::JumpIfFalse, operator.span());
+                    let end_jump = self.emit_jump(OpCode::Jump, operator.span());
+
+                    self.patch_jump(else_jump, operator.span());
+                    self.emit(OpCode::Pop, operator.span());
+                    self.expression(right);
+                    self.patch_jump(end_jump, operator.span());
+                } else {
+                    let end_jump = self.emit_jump(OpCode::JumpIfFalse, operator.span());
+
+                    self.emit(OpCode::Pop, operator.span());
+                    self.expression(right);
+                    self.patch_jump(end_jump, operator.span());
+                }
+            },
+            Expr::Map { brace, entries, .. } => {
+                for (key, value) in entries.iter() {
+                    self.expression(key);
+                    self.expression(value);
+                }
+
+                // Invalid keys are reported at the opening brace.
+                self.emit_count(OpCode::Map, entries.len(), u16::MAX as usize, brace.span(), "Too many entries in map literal.");
+            },
+            Expr::Set { object, name, value } => {
+                self.expression(object);
+                self.expression(value);
+                let constant = self.identifier_constant(&name.lexeme, name.span());
+                self.emit_short(OpCode::SetProperty, constant, name.span());
+            },
+            Expr::SetIndex { object, bracket, index, value } => {
+                self.expression(object);
+                self.expression(index);
+                self.expression(value);
+                self.emit(OpCode::SetIndex, bracket.span());
+            },
+            Expr::Super { keyword, method, .. } => {
+                self.get_variable("this", keyword.span());
+                self.get_variable("super", keyword.span());
+                let constant = self.identifier_constant(&method.lexeme, method.span());
+                self.emit_short(OpCode::GetSuper, constant, method.span());
+            },
+            Expr::This { keyword, .. } => self.get_variable("this", keyword.span()),
+            Expr::Unary { operator, right } => {
+                self.expression(right);
+
+                match operator.token_type {
+                    TokenType::Minus => self.emit(OpCode::Negate, operator.span()),
+                    _ => self.emit(OpCode::Not, operator.span()),
+                }
+            },
+            Expr::Variable { name, .. } => self.get_variable(&name.lexeme, name.span()),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt::Write;
+
+use crate::environment::Object;
+
+use super::{chunk::{Chunk, OpCode}, function::Function};
+
+/// Renders compiled chunks as a human readable listing, one instruction per
+/// line with its offset, source line, operands and constant values.
+pub struct Disassembler {
+    output: String,
+}
+
+impl Default for Disassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Disassembler {
+    pub fn new() -> Self {
+        Self { output: String::new() }
+    }
+
+    /// Lists a function followed by every function nested in its constants.
+    pub fn disassemble(mut self, function: &Function) -> String {
+        self.function(function);
+        self.output
+    }
+
+    /// Lists the single instruction at `offset`, returning it together with
+    /// the offset of the next instruction.
+    pub fn instruction(chunk: &Chunk, offset: usize) -> (String, usize) {
+        let mut disassembler = Self::new();
+        let next = disassembler.write_instruction(chunk, offset);
+        (disassembler.output, next)
+    }
+
+    fn function(&mut self, function: &Function) {
+        writeln!(self.output, "== {} ==", function).unwrap();
+
+        let chunk = &function.chunk;
+        let mut offset = 0;
+
+        while offset < chunk.code.len() {
+            offset = self.write_instruction(chunk, offset);
+            self.output.push('\n');
+        }
+
+        for constant in chunk.constants.iter() {
+            if let Object::Closure(closure) = constant {
+                self.output.push('\n');
+                self.function(&closure.function);
+            }
+        }
+    }
+
+    fn write_instruction(&mut self, chunk: &Chunk, offset: usize) -> usize {
+        write!(self.output, "{:04} ", offset).unwrap();
+
+        let line = chunk.line_at(offset);
+        if offset > 0 && line == chunk.line_at(offset - 1) {
+            write!(self.output, "   | ").unwrap();
+        } else {
+            write!(self.output, "{:4} ", line).unwrap();
+        }
+
+        let byte = chunk.code[offset];
+        let Some(op) = OpCode::from_byte(byte) else {
+            write!(self.output, "Unknown opcode {}", byte).unwrap();
+            return offset + 1;
+        };
+
+        let name = format!("{:?}", op);
+
+        match op {
+            OpCode::Constant | OpCode::GetGlobal | OpCode::DefineGlobal | OpCode::SetGlobal
+            | OpCode::CheckInitialized | OpCode::GetProperty | OpCode::SetProperty | OpCode::GetSuper => {
+                self.constant(&name, chunk, offset)
+            },
+            OpCode::GetLocal | OpCode::SetLocal | OpCode::GetUpvalue | OpCode::SetUpvalue
+            | OpCode::Call | OpCode::Interpolate => {
+                write!(self.output, "{:<16} {:4}", name, chunk.code[offset + 1]).unwrap();
+                offset + 2
+            },
+            OpCode::List | OpCode::Map => {
+                write!(self.output, "{:<16} {:4}", name, chunk.read_short(offset + 1)).unwrap();
+                offset + 3
+            },
+            OpCode::Jump | OpCode::JumpIfFalse => self.jump(&name, chunk, offset, true),
+            OpCode::Loop => self.jump(&name, chunk, offset, false),
+            OpCode::Closure => self.closure(&name, chunk, offset),
+            OpCode::Class => {
+                let next = self.constant(&name, chunk, offset);
+                let count = chunk.code[next];
+                let inherits = chunk.code[next + 1] == 1;
+                write!(self.output, " methods {}{}", count, if inherits { " inherits" } else { "" }).unwrap();
+                next + 2
+            },
+            _ => {
+                write!(self.output, "{}", name).unwrap();
+                offset + 1
+            },
+        }
+    }
+
+    fn constant(&mut self, name: &str, chunk: &Chunk, offset: usize) -> usize {
+        let index = chunk.read_short(offset + 1);
+        write!(self.output, "{:<16} {:4} '{}'", name, index, chunk.constants[index as usize]).unwrap();
+        offset + 3
+    }
+
+    fn jump(&mut self, name: &str, chunk: &Chunk, offset: usize, forward: bool) -> usize {
+        let jump = chunk.read_short(offset + 1) as usize;
+        let next = offset + 3;
+        let target = if forward { next + jump } else { next - jump };
+
+        write!(self.output, "{:<16} {:4} -> {}", name, offset, target).unwrap();
+        next
+    }
+
+    fn closure(&mut self, name: &str, chunk: &Chunk, offset: usize) -> usize {
+        let mut next = self.constant(name, chunk, offset);
+
+        let Object::Closure(template) = &chunk.constants[chunk.read_short(offset + 1) as usize] else {
+            return next;
+        };
+
+        for _ in 0..template.function.upvalue_count {
+            let kind = if chunk.code[next] == 1 { "local" } else { "upvalue" };
+            write!(self.output, "\n{:04}    |                     {} {}", next, kind, chunk.code[next + 1]).unwrap();
+            next += 2;
+        }
+
+        next
+    }
+}
@@ -0,0 +1,53 @@
+use std::{cell::RefCell, fmt::Display, rc::Rc};
+
+use crate::environment::Object;
+
+use super::chunk::Chunk;
+
+/// A function compiled to bytecode.
+#[derive(Debug, Default)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "<script>")
+        } else {
+            write!(f, "<fn {}>", self.name)
+        }
+    }
+}
+
+/// Variable captured by a closure. It points into the VM stack while the
+/// variable's scope is active and holds the value itself once it ends.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Object),
+}
+
+/// A function together with the variables it captured. Function constants
+/// in a chunk are closures without upvalues, used as templates.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl Closure {
+    pub fn new(function: Rc<Function>) -> Self {
+        Self { function, upvalues: Vec::new() }
+    }
+}
+
+/// A method accessed through an instance, called with `this` bound to it.
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Object,
+    pub method: Rc<Closure>,
+}
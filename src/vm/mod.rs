@@ -0,0 +1,11 @@
+//! Bytecode backend: compiles the syntax tree into chunks of bytecode and
+//! runs them on a stack-based virtual machine.
+
+pub mod chunk;
+pub mod compiler;
+pub mod disassembler;
+pub mod function;
+#[allow(clippy::module_inception)]
+pub mod vm;
+
+pub use vm::Vm;
@@ -0,0 +1,591 @@
+use std::{cell::RefCell, collections::HashMap, io::{self, Write}, rc::Rc};
+
+use crate::{environment::{class::{Class, Instance}, environment::Environment, heap::Heap, map::{Map, MapKey}, Object}, error::RuntimeError, interpreter::{Interpreter, DEFAULT_MAX_DEPTH}, parser::Parser, resolver::Resolver, scanner::Scanner, token::TokenType, LoxError};
+
+use super::{chunk::OpCode, compiler::Compiler, disassembler::Disassembler, function::{BoundMethod, Closure, Function, Upvalue}};
+
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// Stack index of the frame's slot zero.
+    slots: usize,
+}
+
+/// Stack-based virtual machine running compiled chunks. Globals persist
+/// between runs.
+pub struct Vm {
+    stack: Vec<Object>,
+    frames: Vec<CallFrame>,
+    globals: HashMap<String, Object>,
+    /// Builtins, looked up after the globals.
+    prelude: HashMap<String, Object>,
+    /// Upvalues still pointing into the stack, ordered by slot.
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    output: Box<dyn Write>,
+    /// Print the stack and each instruction to stderr before running it.
+    trace: bool,
+    /// Calls that may be nested before a call fails with a stack overflow.
+    max_depth: usize,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        let prelude = Environment::prelude().values.into_iter()
+            .map(|(name, value)| (name, *value))
+            .collect();
+
+        Self {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: HashMap::new(),
+            prelude,
+            open_upvalues: Vec::new(),
+            output: Box::new(io::stdout()),
+            trace: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Redirects the output of `print` statements.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Enables the per-instruction execution trace.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    /// Compiles a program to bytecode, rejecting it with the same syntax
+    /// and resolution errors as the tree-walking interpreter.
+    pub fn compile(source: &str) -> Result<Rc<Function>, LoxError> {
+        let mut scanner = Scanner::new(source.to_owned());
+        scanner.scan_tokens();
+
+        let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
+        let stmts = parser.parse_program();
+
+        let mut errors = scanner.errors;
+        errors.extend(parser.diagnostics());
+        if !errors.is_empty() {
+            return Err(LoxError::Compile(errors));
+        }
+
+        // The resolver only checks for errors here; the compiler resolves
+        // variables to stack slots on its own.
+        let mut interpreter = Interpreter::new();
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve(&stmts);
+
+        if !resolver.errors().is_empty() {
+            return Err(LoxError::Compile(resolver.errors().to_vec()));
+        }
+
+        Compiler::new().compile(&stmts).map_err(LoxError::Compile)
+    }
+
+    pub fn run_source(&mut self, source: &str) -> Result<(), LoxError> {
+        let function = Self::compile(source)?;
+        self.interpret(function)?;
+        Ok(())
+    }
+
+    /// Runs a compiled script.
+    pub fn interpret(&mut self, function: Rc<Function>) -> Result<(), RuntimeError> {
+        let closure = Rc::new(Closure::new(function));
+        self.stack.push(Object::Closure(closure.clone()));
+        self.frames.push(CallFrame { closure, ip: 0, slots: 0 });
+
+        let result = self.run();
+
+        if result.is_err() {
+            self.stack.clear();
+            self.frames.clear();
+            self.open_upvalues.clear();
+        }
+
+        result
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("No function is running.")
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frames.last_mut().expect("No function is running.");
+        let byte = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_short(&mut self) -> u16 {
+        let frame = self.frames.last_mut().expect("No function is running.");
+        let short = frame.closure.function.chunk.read_short(frame.ip);
+        frame.ip += 2;
+        short
+    }
+
+    fn read_constant(&mut self) -> Object {
+        let index = self.read_short() as usize;
+        self.frame().closure.function.chunk.constants[index].to_owned()
+    }
+
+    fn read_string(&mut self) -> String {
+        match self.read_constant() {
+            Object::String(name) => name,
+            other => panic!("Expected a name constant, found {}.", other),
+        }
+    }
+
+    fn push(&mut self, value: Object) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> Object {
+        self.stack.pop().expect("Stack underflow.")
+    }
+
+    fn peek(&self, distance: usize) -> &Object {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    /// Runtime error at the instruction being executed, with the active
+    /// calls as the trace when it happens inside a function.
+    fn error(&self, message: String) -> RuntimeError {
+        let span = self.frame().closure.function.chunk.span_at(self.frame().ip - 1);
+        let mut error = RuntimeError::at(span, message);
+        self.traceback(&mut error);
+        error
+    }
+
+    /// Appends every active call to the trace, each with the line it had
+    /// reached, innermost first.
+    fn traceback(&self, error: &mut RuntimeError) {
+        for frame in self.frames.iter().rev() {
+            let function = &frame.closure.function;
+            let name = if function.name.is_empty() { "<script>" } else { &function.name };
+            error.trace.push(format!("{} (line {})", name, function.chunk.line_at(frame.ip - 1)));
+        }
+    }
+
+    /// Prints the stack and the instruction about to run.
+    fn trace_instruction(&self) {
+        let stack = self.stack.iter()
+            .map(|value| format!("[ {} ]", value))
+            .collect::<String>();
+        eprintln!("          {}", stack);
+
+        let frame = self.frame();
+        let (instruction, _) = Disassembler::instruction(&frame.closure.function.chunk, frame.ip);
+        eprintln!("{}", instruction);
+    }
+
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        loop {
+            if self.trace {
+                self.trace_instruction();
+            }
+
+            let byte = self.read_byte();
+            let op = OpCode::from_byte(byte).unwrap_or_else(|| panic!("Invalid opcode {}.", byte));
+
+            match op {
+                OpCode::Constant => {
+                    let value = self.read_constant();
+                    self.push(value);
+                },
+                OpCode::Nil => self.push(Object::Nil),
+                OpCode::True => self.push(Object::Boolean(true)),
+                OpCode::False => self.push(Object::Boolean(false)),
+                OpCode::Pop => {
+                    self.pop();
+                },
+                OpCode::GetLocal => {
+                    let slot = self.frame().slots + self.read_byte() as usize;
+                    self.push(self.stack[slot].to_owned());
+                },
+                OpCode::SetLocal => {
+                    let slot = self.frame().slots + self.read_byte() as usize;
+                    self.stack[slot] = self.peek(0).to_owned();
+                },
+                OpCode::GetGlobal => {
+                    let name = self.read_string();
+
+                    match self.globals.get(&name).or_else(|| self.prelude.get(&name)) {
+                        Some(Object::Unitialized) => return Err(self.error(format!("Variable '{}' has not been initialized or assigned to.", name))),
+                        Some(value) => self.push(value.to_owned()),
+                        None => return Err(self.error(format!("Undefined variable '{}'.", name))),
+                    }
+                },
+                OpCode::DefineGlobal => {
+                    let name = self.read_string();
+
+                    if self.globals.contains_key(&name) {
+                        return Err(self.error(format!("Variable '{}' already defined.", name)));
+                    }
+
+                    let value = self.pop();
+                    self.globals.insert(name, value);
+                },
+                OpCode::SetGlobal => {
+                    let name = self.read_string();
+                    let value = self.peek(0).to_owned();
+
+                    let current = match self.globals.get_mut(&name) {
+                        Some(current) => Some(current),
+                        None => self.prelude.get_mut(&name),
+                    };
+
+                    match current {
+                        Some(current) => *current = value,
+                        None => return Err(self.error(format!("Undefined variable '{}'.", name))),
+                    }
+                },
+                OpCode::GetUpvalue => {
+                    let index = self.read_byte() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+
+                    let value = match &*upvalue.borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].to_owned(),
+                        Upvalue::Closed(value) => value.to_owned(),
+                    };
+                    self.push(value);
+                },
+                OpCode::SetUpvalue => {
+                    let index = self.read_byte() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = self.peek(0).to_owned();
+
+                    let mut upvalue = upvalue.borrow_mut();
+                    match &mut *upvalue {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                },
+                OpCode::CheckInitialized => {
+                    let name = self.read_string();
+
+                    if matches!(self.peek(0), Object::Unitialized) {
+                        return Err(self.error(format!("Variable '{}' has not been initialized or assigned to.", name)));
+                    }
+                },
+                OpCode::GetProperty => {
+                    let name = self.read_string();
+
+                    let Object::Instance(instance) = self.peek(0).to_owned() else {
+                        return Err(self.error(String::from("Only instances have properties.")));
+                    };
+
+                    let field = instance.borrow().field(&name).map(|value| *value.to_owned());
+                    let value = match field {
+                        Some(value) => value,
+                        None => {
+                            let class = instance.borrow().class.clone();
+                            self.bind_method(&class, &name, Object::Instance(instance))?
+                        },
+                    };
+
+                    self.pop();
+                    self.push(value);
+                },
+                OpCode::SetProperty => {
+                    let name = self.read_string();
+
+                    let Object::Instance(instance) = self.peek(1).to_owned() else {
+                        return Err(self.error(String::from("Only instances have fields.")));
+                    };
+
+                    let value = self.pop();
+                    instance.borrow_mut().set_field(&name, Box::new(value.to_owned()));
+                    self.pop();
+                    self.push(value);
+                },
+                OpCode::GetSuper => {
+                    let name = self.read_string();
+
+                    let Object::Class(superclass) = self.pop() else {
+                        return Err(self.error(String::from("Superclass must be a class.")));
+                    };
+
+                    let receiver = self.pop();
+                    let method = self.bind_method(&superclass, &name, receiver)?;
+                    self.push(method);
+                },
+                OpCode::GetIndex => {
+                    let index = self.pop();
+                    let object = self.pop();
+
+                    let value = object.get_index(&index).map_err(|message| self.error(message))?;
+                    self.push(*value);
+                },
+                OpCode::SetIndex => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let object = self.pop();
+
+                    object.set_index(&index, Box::new(value.to_owned())).map_err(|message| self.error(message))?;
+                    self.push(value);
+                },
+                OpCode::Equal => self.binary(TokenType::EqualEqual)?,
+                OpCode::NotEqual => self.binary(TokenType::BangEqual)?,
+                OpCode::Greater => self.binary(TokenType::Greater)?,
+                OpCode::GreaterEqual => self.binary(TokenType::GreaterEqual)?,
+                OpCode::Less => self.binary(TokenType::Less)?,
+                OpCode::LessEqual => self.binary(TokenType::LessEqual)?,
+                OpCode::Add => self.binary(TokenType::Plus)?,
+                OpCode::Subtract => self.binary(TokenType::Minus)?,
+                OpCode::Multiply => self.binary(TokenType::Star)?,
+                OpCode::Divide => self.binary(TokenType::Slash)?,
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.push(Object::Boolean(!value.is_thuthy()));
+                },
+                OpCode::Negate => {
+                    let Object::Number(value) = self.peek(0) else {
+                        return Err(self.error(String::from("Operand must be a number.")));
+                    };
+
+                    let value = -value;
+                    self.pop();
+                    self.push(Object::Number(value));
+                },
+                OpCode::Print => {
+                    let value = self.pop();
+                    writeln!(self.output, "{}", value).expect("Failed to write output.");
+                },
+                OpCode::Jump => {
+                    let offset = self.read_short() as usize;
+                    self.frames.last_mut().expect("No function is running.").ip += offset;
+                },
+                OpCode::JumpIfFalse => {
+                    let offset = self.read_short() as usize;
+
+                    if !self.peek(0).is_thuthy() {
+                        self.frames.last_mut().expect("No function is running.").ip += offset;
+                    }
+                },
+                OpCode::Loop => {
+                    let offset = self.read_short() as usize;
+                    self.frames.last_mut().expect("No function is running.").ip -= offset;
+                },
+                OpCode::Call => {
+                    let count = self.read_byte() as usize;
+                    self.call_value(count)?;
+                },
+                OpCode::Closure => {
+                    let Object::Closure(template) = self.read_constant() else {
+                        panic!("Expected a function constant.");
+                    };
+
+                    let mut closure = Closure::new(template.function.clone());
+
+                    for _ in 0..template.function.upvalue_count {
+                        let is_local = self.read_byte() == 1;
+                        let index = self.read_byte() as usize;
+
+                        let upvalue = if is_local {
+                            self.capture_upvalue(self.frame().slots + index)
+                        } else {
+                            self.frame().closure.upvalues[index].clone()
+                        };
+                        closure.upvalues.push(upvalue);
+                    }
+
+                    self.push(Object::Closure(Rc::new(closure)));
+                },
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                },
+                OpCode::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("No function is running.");
+
+                    self.close_upvalues(frame.slots);
+                    self.stack.truncate(frame.slots);
+
+                    if self.frames.is_empty() {
+                        return Ok(());
+                    }
+
+                    self.push(result);
+                },
+                OpCode::Class => {
+                    let name = self.read_string();
+                    let count = self.read_byte() as usize;
+                    let inherits = self.read_byte() == 1;
+
+                    let methods = self.stack.split_off(self.stack.len() - count).into_iter()
+                        .map(|method| match method {
+                            Object::Closure(closure) => (closure.function.name.to_owned(), Box::new(Object::Closure(closure))),
+                            other => panic!("Expected a method, found {}.", other),
+                        })
+                        .collect::<HashMap<String, Box<Object>>>();
+
+                    let superclass = match (inherits, self.stack.last()) {
+                        (true, Some(Object::Class(superclass))) => Some(superclass.clone()),
+                        (true, _) => return Err(self.error(String::from("Superclass must be a class."))),
+                        (false, _) => None,
+                    };
+
+                    self.push(Object::Class(Rc::new(Class::new(name, superclass, methods))));
+                },
+                OpCode::Interpolate => {
+                    let count = self.read_byte() as usize;
+
+                    let value = self.stack.split_off(self.stack.len() - count).iter()
+                        .map(|part| part.to_string())
+                        .collect::<String>();
+                    self.push(Object::String(value));
+                },
+                OpCode::List => {
+                    let count = self.read_short() as usize;
+
+                    let elements = self.stack.split_off(self.stack.len() - count).into_iter()
+                        .map(Box::new)
+                        .collect();
+                    self.push(Object::List(Heap::list(elements)));
+                },
+                OpCode::Map => {
+                    let count = self.read_short() as usize;
+                    let entries = self.stack.split_off(self.stack.len() - count * 2);
+
+                    let mut map = Map::new();
+                    for entry in entries.chunks(2) {
+                        let key = MapKey::new(&entry[0]).map_err(|message| self.error(message))?;
+                        map.insert(key, Box::new(entry[1].to_owned()));
+                    }
+
+                    self.push(Object::Map(Heap::map(map)));
+                },
+            }
+        }
+    }
+
+    fn binary(&mut self, operator: TokenType) -> Result<(), RuntimeError> {
+        let right = self.pop();
+        let left = self.pop();
+
+        let value = Object::binary(left, &operator, right).map_err(|message| self.error(message))?;
+        self.push(value);
+
+        Ok(())
+    }
+
+    /// Looks up a method on `class` and binds it to `receiver`.
+    fn bind_method(&self, class: &Class, name: &str, receiver: Object) -> Result<Object, RuntimeError> {
+        match class.find_method(name).map(|method| *method) {
+            Some(Object::Closure(method)) => Ok(Object::BoundMethod(Rc::new(BoundMethod { receiver, method }))),
+            _ => Err(self.error(format!("Undefined property '{}'.", name))),
+        }
+    }
+
+    fn call_value(&mut self, count: usize) -> Result<(), RuntimeError> {
+        let base = self.stack.len() - count - 1;
+
+        match self.stack[base].to_owned() {
+            Object::Closure(closure) => self.call(closure, count),
+            Object::BoundMethod(bound) => {
+                self.stack[base] = bound.receiver.to_owned();
+                self.call(bound.method.clone(), count)
+            },
+            Object::Class(class) => {
+                self.stack[base] = Object::Instance(Heap::instance(Instance::new(class.clone())));
+
+                match class.find_method("init").map(|method| *method) {
+                    Some(Object::Closure(initializer)) => self.call(initializer, count),
+                    _ if count != 0 => Err(self.error(format!("Expected 0 arguments, but got {}.", count))),
+                    _ => Ok(()),
+                }
+            },
+            Object::Builtin(name, arity, function) => {
+                if count != arity {
+                    return Err(self.error(format!("Expected {} arguments, but got {}.", arity, count)));
+                }
+
+                self.check_depth()?;
+
+                let arguments = self.stack.split_off(base + 1).into_iter()
+                    .map(Box::new)
+                    .collect::<Box<[Box<Object>]>>();
+
+                match function(arguments) {
+                    Ok(value) => {
+                        self.pop();
+                        self.push(*value);
+                        Ok(())
+                    },
+                    Err(message) => {
+                        let span = self.frame().closure.function.chunk.span_at(self.frame().ip - 1);
+                        let mut error = RuntimeError::at(span, message);
+                        error.trace.push(format!("{} (line {})", name, span.line));
+                        self.traceback(&mut error);
+                        Err(error)
+                    },
+                }
+            },
+            callee => Err(self.error(format!("Can only call functions and classes, but tried {}.", callee))),
+        }
+    }
+
+    fn call(&mut self, closure: Rc<Closure>, count: usize) -> Result<(), RuntimeError> {
+        if count != closure.function.arity {
+            return Err(self.error(format!("Expected {} arguments, but got {}.", closure.function.arity, count)));
+        }
+
+        self.check_depth()?;
+
+        self.frames.push(CallFrame { closure, ip: 0, slots: self.stack.len() - count - 1 });
+        Ok(())
+    }
+
+    /// Fails if another call would nest too deeply. The script itself runs
+    /// in the first frame and doesn't count.
+    fn check_depth(&self) -> Result<(), RuntimeError> {
+        if self.frames.len() > self.max_depth {
+            return Err(self.error(String::from("Stack overflow.")));
+        }
+
+        Ok(())
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let position = self.open_upvalues.iter().position(|upvalue| match &*upvalue.borrow() {
+            Upvalue::Open(open) => *open >= slot,
+            Upvalue::Closed(_) => false,
+        });
+
+        if let Some(position) = position {
+            if matches!(&*self.open_upvalues[position].borrow(), Upvalue::Open(open) if *open == slot) {
+                return self.open_upvalues[position].clone();
+            }
+        }
+
+        let upvalue = Heap::upvalue(Upvalue::Open(slot));
+        self.open_upvalues.insert(position.unwrap_or(self.open_upvalues.len()), upvalue.clone());
+        upvalue
+    }
+
+    /// Moves the values of the upvalues pointing at `from` or above off the stack.
+    fn close_upvalues(&mut self, from: usize) {
+        while let Some(upvalue) = self.open_upvalues.last() {
+            let slot = match &*upvalue.borrow() {
+                Upvalue::Open(slot) if *slot >= from => *slot,
+                _ => break,
+            };
+
+            let upvalue = self.open_upvalues.pop().expect("Checked above.");
+            *upvalue.borrow_mut() = Upvalue::Closed(self.stack[slot].to_owned());
+        }
+    }
+}
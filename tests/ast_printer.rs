@@ -0,0 +1,72 @@
+//! Checks the parenthesized form `parse` prints for expressions and programs.
+
+use interpreter_starter_rust::{ast_printer::AstPrinter, parser::Parser, scanner::Scanner};
+
+fn parser(source: &str) -> Parser {
+    let mut scanner = Scanner::new(source.to_owned());
+    scanner.scan_tokens();
+
+    Parser::new(scanner.tokens.into_boxed_slice())
+}
+
+fn print_expr(source: &str) -> String {
+    let expr = parser(source).parse_expr().expect("Expression failed to parse.");
+    AstPrinter::new().print(&expr)
+}
+
+fn print_program(source: &str) -> String {
+    let mut parser = parser(source);
+    let stmts = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+    AstPrinter::new().print_program(&stmts)
+}
+
+#[test]
+fn prints_operators_by_precedence() {
+    assert_eq!(
+        print_expr("-(1 + 2) * x.y[0] == !f(a, \"s\") or ok"),
+        "(or (== (* (- (group (+ 1 2))) (index (. x y) 0)) (! (call f a s))) ok)",
+    );
+}
+
+#[test]
+fn prints_assignments_and_collections() {
+    assert_eq!(
+        print_expr("m.k = {\"k\": [1, 2], 3: nil}[0] = x = true"),
+        "(= (. m k) (= (index (map (: k (list 1 2)) (: 3 nil)) 0) (= x true)))",
+    );
+}
+
+#[test]
+fn prints_interpolation_parts() {
+    assert_eq!(print_expr("\"n = ${n}!\""), "(interpolate n =  n !)");
+}
+
+#[test]
+fn prints_declarations() {
+    let source = "\
+var a;
+var b = fun (x) { return x; };
+fun f() { return; }
+class B < A { m() { super.m(); this.v = 1; } }";
+
+    assert_eq!(print_program(source), "\
+(var a)
+(var b = (lambda (x) (return x)))
+(fun f() (return))
+(class B < A (fun m() (; (call (super m))) (; (= (. this v) 1))))");
+}
+
+#[test]
+fn prints_control_flow() {
+    let source = "\
+for (var i = 0; i < 3; i = i + 1) { if (i == 1) continue; else break; }
+while (false) print 1;
+if (a) { }";
+
+    assert_eq!(print_program(source), "\
+(block (var i = 0) (while (< i 3) (block (if-else (== i 1) (continue) (break))) (= i (+ i 1))))
+(while false (print 1))
+(if a (block))");
+}
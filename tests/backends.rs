@@ -0,0 +1,117 @@
+//! Runs every program in `tests/corpus` on the tree-walking interpreter and
+//! on the bytecode VM, and checks that both print the same output and fail
+//! with the same error.
+
+use std::{cell::RefCell, fs, io::{self, Write}, path::Path, rc::Rc};
+
+use interpreter_starter_rust::{interpreter::{stack_size, DEFAULT_MAX_DEPTH}, vm::Vm, Lox, LoxError};
+
+/// Output shared with the interpreter that writes it.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What running a program produced: its output, then how it failed.
+fn outcome(output: Output, result: Result<(), LoxError>) -> String {
+    let mut outcome = String::from_utf8(output.0.take()).expect("Output is not UTF-8.");
+
+    match result {
+        Ok(()) => {},
+        Err(LoxError::Compile(errors)) => {
+            for error in errors.iter() {
+                outcome.push_str(&format!("{}\n", error));
+            }
+        },
+        Err(LoxError::Runtime(error)) => {
+            outcome.push_str(&format!("{}\n", error));
+            for frame in error.trace.iter() {
+                outcome.push_str(&format!("    at {}\n", frame));
+            }
+        },
+    }
+
+    outcome
+}
+
+/// Runs `source` on the given backend, on a thread with enough stack for
+/// the tree-walker to reach the call depth limit.
+fn run(source: String, backend: &'static str) -> String {
+    let size = stack_size(DEFAULT_MAX_DEPTH).expect("Default call depth needs too much stack.");
+
+    std::thread::Builder::new().stack_size(size).spawn(move || {
+        let output = Output::default();
+
+        let result = match backend {
+            "vm" => {
+                let mut vm = Vm::new();
+                vm.set_output(Box::new(output.clone()));
+                vm.run_source(&source)
+            },
+            _ => Lox::with_output(output.clone()).run_source(&source).map(|_| ()),
+        };
+
+        outcome(output, result)
+    })
+    .expect("Failed to start the interpreter thread.")
+    .join()
+    .expect("Interpreter thread panicked.")
+}
+
+#[test]
+fn backends_agree_on_corpus() {
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut paths = fs::read_dir(&corpus).expect("Failed to read the corpus.")
+        .map(|entry| entry.expect("Failed to read the corpus.").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "lox"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    assert!(!paths.is_empty(), "The corpus is empty.");
+
+    for path in paths {
+        let source = fs::read_to_string(&path).expect("Failed to read a corpus program.");
+
+        let tree = run(source.clone(), "tree");
+        let vm = run(source, "vm");
+
+        assert_eq!(tree, vm, "Backends disagree on {}", path.display());
+    }
+}
+
+#[test]
+fn traces_end_with_the_script_frame() {
+    for backend in ["tree", "vm"] {
+        let top_level = run(String::from("print nil + 1;"), backend);
+        assert!(top_level.ends_with("    at <script> (line 1)\n"), "{}: {}", backend, top_level);
+
+        let nested = run(String::from("fun f() {\n  return nil + 1;\n}\nf();"), backend);
+        assert!(nested.ends_with("    at f (line 2)\n    at <script> (line 4)\n"), "{}: {}", backend, nested);
+    }
+}
+
+#[test]
+fn interpolation_stringifies_each_part() {
+    let source = r#"
+var n = 2;
+print "n=${n}, half=${n / 4}, nested=${"in${n + 1}ner"}";
+print "${nil} ${true} ${[1, "a"]} ${ {"k": "v"}["k"] }";
+print "\${n} costs $5";
+"#;
+
+    for backend in ["tree", "vm"] {
+        assert_eq!(run(String::from(source), backend), "\
+n=2, half=0.5, nested=in3ner
+nil true [1, a] v
+${n} costs $5
+", "{}", backend);
+    }
+}
@@ -0,0 +1,123 @@
+//! Checks that `fmt` produces canonical source, keeps comments, and leaves
+//! its own output unchanged.
+
+use interpreter_starter_rust::{formatter::Formatter, parser::Parser, scanner::Scanner};
+
+fn format(source: &str) -> String {
+    let mut scanner = Scanner::new(source.to_owned());
+    scanner.scan_tokens();
+    assert!(scanner.errors.is_empty(), "{:?}", scanner.errors);
+
+    let mut parser = Parser::new(scanner.tokens.into_boxed_slice()).with_comments();
+    let stmts = parser.parse_program();
+    assert!(parser.errors().is_empty(), "{:?}", parser.errors());
+
+    Formatter::new().format(&stmts)
+}
+
+/// Formats `source` and checks that formatting the result changes nothing.
+fn format_twice(source: &str) -> String {
+    let formatted = format(source);
+    assert_eq!(format(&formatted), formatted, "Formatting is not idempotent.");
+
+    formatted
+}
+
+#[test]
+fn normalizes_spacing_and_indentation() {
+    let source = "\
+var   x=1;
+fun double(n){return n*2;}
+class A<B{m(){print this.v;}n(){}}
+var m={\"a\":[1,2],};
+var l=fun(a,b){return a+b;};";
+
+    assert_eq!(format_twice(source), "\
+var x = 1;
+
+fun double(n) {
+    return n * 2;
+}
+
+class A < B {
+    m() {
+        print this.v;
+    }
+
+    n() {}
+}
+
+var m = {\"a\": [1, 2]};
+var l = fun (a, b) {
+    return a + b;
+};
+");
+}
+
+#[test]
+fn keeps_the_form_of_loops_and_branches() {
+    let source = "\
+for(;;){break;}
+for(var i=0;i<2;i=i+1)print i;
+for(x=0;x<1;){x=x+1;}
+if(x)print 1;else{print 2;}
+while(false)print \"${x}!\";";
+
+    assert_eq!(format_twice(source), "\
+for (;;) {
+    break;
+}
+for (var i = 0; i < 2; i = i + 1)
+    print i;
+for (x = 0; x < 1;) {
+    x = x + 1;
+}
+if (x)
+    print 1;
+else {
+    print 2;
+}
+while (false)
+    print \"${x}!\";
+");
+}
+
+#[test]
+fn preserves_comments() {
+    let source = "\
+// Leading comment.
+var   x=1;   // trailing
+/* block */
+/// Doubles.
+fun double(n){
+  // only a comment
+}
+class A{
+  // about m
+  m(){}
+}
+{print 1; /* after */
+// last
+}";
+
+    assert_eq!(format_twice(source), "\
+// Leading comment.
+var x = 1; // trailing
+
+/* block */
+/// Doubles.
+fun double(n) {
+    // only a comment
+}
+
+class A {
+    // about m
+    m() {}
+}
+
+{
+    print 1; /* after */
+    // last
+}
+");
+}
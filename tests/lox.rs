@@ -0,0 +1,145 @@
+//! Exercises the embedding API: sessions, captured output, host functions
+//! and the errors handed back to the caller.
+
+use std::{cell::RefCell, io::{self, Write}, rc::Rc};
+
+use interpreter_starter_rust::{environment::{Args, BObject}, Lox, LoxError, Value};
+
+/// Output shared with the session that writes it.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Output {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).expect("Output is not UTF-8.")
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[allow(clippy::boxed_local)]
+fn double(arguments: Args) -> Result<BObject, String> {
+    match arguments[0].as_ref() {
+        Value::Number(n) => Ok(Box::new(Value::Number(n * 2.0))),
+        other => Err(format!("Expected a number, got {}.", other)),
+    }
+}
+
+#[test]
+fn returns_the_value_of_the_final_expression() {
+    let mut lox = Lox::with_output(Output::default());
+
+    let value = lox.run_source("var x = 20; x + 22;").expect("Program failed.");
+    assert!(matches!(value, Value::Number(n) if n == 42.0), "{}", value);
+
+    let value = lox.run_source("var y = 1;").expect("Program failed.");
+    assert!(matches!(value, Value::Nil), "{}", value);
+}
+
+#[test]
+fn captures_print_output() {
+    let output = Output::default();
+    let mut lox = Lox::with_output(output.clone());
+
+    lox.run_source("print \"hello\"; print 1 + 2;").expect("Program failed.");
+
+    assert_eq!(output.text(), "hello\n3\n");
+}
+
+#[test]
+fn keeps_globals_between_runs() {
+    let output = Output::default();
+    let mut lox = Lox::with_output(output.clone());
+
+    lox.run_source("var count = 1;").expect("Program failed.");
+    lox.run_source("count = count + 1; print count;").expect("Program failed.");
+
+    assert_eq!(output.text(), "2\n");
+    assert!(lox.globals().borrow().values.contains_key("count"));
+}
+
+#[test]
+fn calls_registered_host_functions() {
+    let output = Output::default();
+    let mut lox = Lox::with_output(output.clone());
+    lox.register("double", 1, double);
+
+    lox.run_source("print double(21);").expect("Program failed.");
+    assert_eq!(output.text(), "42\n");
+
+    let Err(LoxError::Runtime(error)) = lox.run_source("double(\"x\");") else {
+        panic!("Expected a runtime error.");
+    };
+    assert_eq!(error.message, "Expected a number, got x.");
+}
+
+#[test]
+fn scripts_may_shadow_registered_functions() {
+    let output = Output::default();
+    let mut lox = Lox::with_output(output.clone());
+    lox.register("double", 1, double);
+
+    lox.run_source("fun double(n) { return n; } print double(2);").expect("Program failed.");
+
+    assert_eq!(output.text(), "2\n");
+}
+
+#[test]
+fn hands_back_every_compile_error() {
+    let mut lox = Lox::with_output(Output::default());
+
+    let Err(LoxError::Compile(errors)) = lox.run_source("var = 1;\nprint (2;\nvar ok = @;") else {
+        panic!("Expected compile errors.");
+    };
+
+    let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+    assert_eq!(messages, [
+        "[line 3] Error: Unexpected character: @",
+        "[line 1] Error at '=': Expect variable name.",
+        "[line 2] Error at ';': Expect ')' after expression.",
+        "[line 3] Error at ';': Expect expression.",
+    ]);
+}
+
+#[test]
+fn reports_resolution_errors_as_compile_errors() {
+    let mut lox = Lox::with_output(Output::default());
+
+    let Err(LoxError::Compile(errors)) = lox.run_source("return 1;") else {
+        panic!("Expected a compile error.");
+    };
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].to_string(), "[line 1] Error at 'return': Can't return from top-level code.");
+}
+
+#[test]
+fn sessions_on_separate_threads_are_independent() {
+    let threads: Vec<_> = (0..4).map(|index| {
+        std::thread::spawn(move || {
+            let output = Output::default();
+            let mut lox = Lox::with_output(output.clone());
+
+            // Every other session fails to compile, and must not affect the rest.
+            let source = if index % 2 == 0 { format!("print {};", index) } else { String::from("print ;") };
+            let compiled = !matches!(lox.run_source(&source), Err(LoxError::Compile(_)));
+
+            (compiled, output.text())
+        })
+    }).collect();
+
+    for (index, thread) in threads.into_iter().enumerate() {
+        let (compiled, text) = thread.join().expect("Session panicked.");
+
+        assert_eq!(compiled, index % 2 == 0);
+        assert_eq!(text, if compiled { format!("{}\n", index) } else { String::new() });
+    }
+}
@@ -0,0 +1,110 @@
+//! Checks which syntax errors the parser reports and how it recovers.
+
+use interpreter_starter_rust::{ast_printer::AstPrinter, parser::Parser, scanner::Scanner, statement::Stmt};
+
+/// Parses `source` as a program and returns every error it reported.
+fn errors(source: &str) -> Vec<String> {
+    let mut scanner = Scanner::new(source.to_owned());
+    scanner.scan_tokens();
+
+    let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
+    parser.parse_program();
+
+    parser.errors().iter().map(|error| error.to_string()).collect()
+}
+
+#[test]
+fn reports_every_broken_method_in_a_class() {
+    let source = "\
+class A {
+  one( { return 1; }
+  two() { return 2; }
+  three(a b) {}
+  four() { return 4; }
+}
+print A;";
+
+    assert_eq!(errors(source), [
+        "[line 2] Error at '{': Expect parameter name.",
+        "[line 4] Error at 'b': Expect ')' after parameters.",
+    ]);
+}
+
+#[test]
+fn keeps_the_methods_around_a_broken_one() {
+    let mut scanner = Scanner::new(String::from("class A {\n  one() {}\n  two( {}\n  three() {}\n}"));
+    scanner.scan_tokens();
+
+    let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
+    let stmts = parser.parse_program();
+
+    assert_eq!(parser.errors().len(), 1);
+
+    let [class] = stmts.as_ref() else {
+        panic!("Expected only the class, got {:?}", stmts);
+    };
+    let Stmt::Class { methods, .. } = class.as_ref() else {
+        panic!("Expected a class, got {:?}", class);
+    };
+
+    let names: Vec<&str> = methods.iter()
+        .filter_map(|method| match method.as_ref() {
+            Stmt::Function { name, .. } => Some(name.lexeme.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(names, ["one", "three"]);
+}
+
+#[test]
+fn attaches_doc_comments_to_declarations() {
+    let mut scanner = Scanner::new(String::from("/// Adds two numbers.\n/// Returns the sum.\nfun add(a, b) { return a + b; }"));
+    scanner.scan_tokens();
+
+    let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
+    let stmts = parser.parse_program();
+
+    let [function] = stmts.as_ref() else {
+        panic!("Expected only the function, got {:?}", stmts);
+    };
+    let Stmt::Function { doc, .. } = function.as_ref() else {
+        panic!("Expected a function, got {:?}", function);
+    };
+
+    assert_eq!(doc.as_deref(), Some("Adds two numbers.\nReturns the sum."));
+}
+
+#[test]
+fn reports_an_error_in_every_broken_statement() {
+    let source = "\
+var = 1;
+print 2;
+fun f() {
+  var a = ;
+  print a;
+  a = 1 +;
+}
+print (3;
+if (true) { print 4 }
+var ok = 5;";
+
+    assert_eq!(errors(source), [
+        "[line 1] Error at '=': Expect variable name.",
+        "[line 4] Error at ';': Expect expression.",
+        "[line 6] Error at ';': Expect expression.",
+        "[line 8] Error at ';': Expect ')' after expression.",
+        "[line 9] Error at '}': Expect ';' after value.",
+    ]);
+}
+
+#[test]
+fn keeps_the_statements_around_broken_ones() {
+    let mut scanner = Scanner::new(String::from("print 1;\nvar = 2;\nfun f() {\n  var a = ;\n  print a;\n}\nprint 3;"));
+    scanner.scan_tokens();
+
+    let mut parser = Parser::new(scanner.tokens.into_boxed_slice());
+    let stmts = parser.parse_program();
+
+    assert_eq!(parser.errors().len(), 2);
+    assert_eq!(AstPrinter::new().print_program(&stmts), "(print 1)\n(fun f() (print a))\n(print 3)");
+}
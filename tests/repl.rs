@@ -0,0 +1,64 @@
+//! Drives the interactive prompt through stdin and checks what it prints.
+
+use std::{io::Write, process::{Command, Stdio}};
+
+/// Feeds `input` to the REPL and returns its stdout without the prompts,
+/// together with its stderr.
+fn repl(input: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_interpreter-starter-rust"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start the REPL.");
+
+    child.stdin.take().expect("No stdin.").write_all(input.as_bytes()).expect("Failed to write input.");
+
+    let output = child.wait_with_output().expect("REPL did not exit.");
+    let stdout = String::from_utf8(output.stdout).expect("Output is not UTF-8.")
+        .replace("... ", "")
+        .replace("> ", "");
+
+    (stdout.trim().to_owned(), String::from_utf8(output.stderr).expect("Output is not UTF-8."))
+}
+
+#[test]
+fn prints_expression_values_without_semicolon() {
+    let (stdout, stderr) = repl("1 + 2\nprint 3\n\"a\" + \"b\";\n");
+
+    assert_eq!(stdout, "3\n3");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn map_literals_and_lambdas_need_no_semicolon() {
+    let (stdout, stderr) = repl("var m = {\"a\": 1}\nm[\"a\"]\n{\"b\": 2}\nvar g = fun () { return 1; }\ng()\n");
+
+    assert_eq!(stdout, "1\n{b: 2}\n1");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn keeps_reading_unclosed_input() {
+    let (stdout, stderr) = repl("var c = [1,\n2];\nfun f() {\nreturn c;\n}\nf()\n");
+
+    assert_eq!(stdout, "[1, 2]");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn reports_errors_on_the_line_typed() {
+    let (stdout, stderr) = repl("1 +\nprint \"still running\";\n");
+
+    assert_eq!(stdout, "still running");
+    assert!(stderr.starts_with("[line 1] Error at ';': Expect expression."), "{}", stderr);
+    assert!(stderr.contains("1 | 1 +;"), "{}", stderr);
+}
+
+#[test]
+fn globals_survive_runtime_errors() {
+    let (stdout, stderr) = repl("var x = 1;\nnil + 1;\nx\n");
+
+    assert_eq!(stdout, "1");
+    assert!(stderr.contains("Runtime Error"), "{}", stderr);
+}
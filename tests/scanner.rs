@@ -0,0 +1,129 @@
+//! Checks the tokens and errors the scanner produces for literals and
+//! identifiers.
+
+use interpreter_starter_rust::scanner::Scanner;
+
+/// Scans `source` and returns the tokens as `tokenize` prints them, and the
+/// errors reported.
+fn scan(source: &str) -> (Vec<String>, Vec<String>) {
+    let mut scanner = Scanner::new(source.to_owned());
+    scanner.scan_tokens();
+
+    let tokens = scanner.tokens.iter().map(|token| token.to_string()).collect();
+    let errors = scanner.errors.iter().map(|error| error.to_string()).collect();
+
+    (tokens, errors)
+}
+
+#[test]
+fn scans_prefixed_and_separated_numbers() {
+    let (tokens, errors) = scan("0x1F 0b1010 0o17 1_000_000 1e-9 2.5E3");
+
+    assert_eq!(tokens, [
+        "NUMBER 0x1F 31.0",
+        "NUMBER 0b1010 10.0",
+        "NUMBER 0o17 15.0",
+        "NUMBER 1_000_000 1000000.0",
+        "NUMBER 1e-9 0.000000001",
+        "NUMBER 2.5E3 2500.0",
+        "EOF  nil",
+    ]);
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn decimal_number_followed_by_letters_is_two_tokens() {
+    let (tokens, errors) = scan("123abc 2e");
+
+    assert_eq!(tokens, ["NUMBER 123 123.0", "IDENTIFIER abc nil", "NUMBER 2 2.0", "IDENTIFIER e nil", "EOF  nil"]);
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn reports_malformed_numbers() {
+    let (_, errors) = scan("0b102 0x 1__0 1_");
+
+    assert_eq!(errors, [
+        "[line 1] Error: Invalid number literal '0b102'.",
+        "[line 1] Error: Expect hexadecimal digits after '0x'.",
+        "[line 1] Error: Invalid digit separator in number literal '1__0'.",
+        "[line 1] Error: Invalid digit separator in number literal '1_'.",
+    ]);
+}
+
+#[test]
+fn keeps_doc_comments_out_of_the_token_stream() {
+    let (tokens, errors) = scan("/// Documented.\n//// Plain.\nvar x; // trailing");
+
+    assert_eq!(tokens, ["VAR var nil", "IDENTIFIER x nil", "SEMICOLON ; nil", "EOF  nil"]);
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn decodes_escape_sequences() {
+    let mut scanner = Scanner::new(String::from(r#""a\tb\nc\r\\\"\0\$\u{e9}\u{1F600}""#));
+    scanner.scan_tokens();
+
+    assert!(scanner.errors.is_empty(), "{:?}", scanner.errors);
+    assert_eq!(scanner.tokens[0].literal.to_string(), "a\tb\nc\r\\\"\0$é😀");
+}
+
+#[test]
+fn reports_malformed_escapes_and_keeps_scanning() {
+    let (tokens, errors) = scan(r#""\q \u{110000} \u{} \u{12" ok"#);
+
+    assert_eq!(errors, [
+        r"[line 1] Error: Invalid escape sequence '\q'.",
+        r"[line 1] Error: Invalid unicode escape '\u{110000}'.",
+        r"[line 1] Error: Invalid unicode escape '\u{}'.",
+        r"[line 1] Error: Invalid unicode escape '\u{12'.",
+    ]);
+    assert_eq!(tokens[1..], ["IDENTIFIER ok nil", "EOF  nil"]);
+}
+
+#[test]
+fn scans_unicode_identifiers() {
+    let (tokens, errors) = scan("été a·b _x1 Δt");
+
+    assert_eq!(tokens, ["IDENTIFIER été nil", "IDENTIFIER a·b nil", "IDENTIFIER _x1 nil", "IDENTIFIER Δt nil", "EOF  nil"]);
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn rejects_characters_outside_identifiers() {
+    let (tokens, errors) = scan("x² ·a");
+
+    assert_eq!(errors, [
+        "[line 1] Error: Unexpected character: ²",
+        "[line 1] Error: Unexpected character: ·",
+    ]);
+    assert_eq!(tokens, ["IDENTIFIER x nil", "IDENTIFIER a nil", "EOF  nil"]);
+}
+
+#[test]
+fn splits_interpolated_strings_around_expressions() {
+    let (tokens, errors) = scan(r#""a${1}b${"in${x}"}c" "${{}}""#);
+
+    assert_eq!(tokens, [
+        r#"INTERPOLATION "a${ a"#,
+        "NUMBER 1 1.0",
+        "INTERPOLATION_MID }b${ b",
+        r#"INTERPOLATION "in${ in"#,
+        "IDENTIFIER x nil",
+        r#"INTERPOLATION_END }" "#,
+        r#"INTERPOLATION_END }c" c"#,
+        r#"INTERPOLATION "${ "#,
+        "LEFT_BRACE { nil",
+        "RIGHT_BRACE } nil",
+        r#"INTERPOLATION_END }" "#,
+        "EOF  nil",
+    ]);
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn reports_an_unclosed_interpolation() {
+    let (_, errors) = scan(r#""open ${1 + 2""#);
+
+    assert_eq!(errors, ["[line 1] Error: Unterminated string."]);
+}